@@ -1,10 +1,12 @@
 #![allow(clippy::single_match)]
 
-use std::{num::NonZeroU32, os::fd::AsRawFd};
+use std::os::fd::AsRawFd;
 
 use slimbus::zvariant::{DeserializeDict, SerializeDict, Type};
-use slimbus::{names::OwnedUniqueName, Connection, Message, Result, SocketReader};
-use zvariant::OwnedObjectPath;
+use slimbus::{
+    names::{OwnedObjectPath, OwnedUniqueName},
+    Connection, Message, Result, SocketReader,
+};
 
 #[derive(serde::Serialize, Type, Debug)]
 pub struct HandleToken(String);
@@ -49,20 +51,6 @@ pub struct Response {
     results: SelectedFiles,
 }
 
-fn hello(connection: &mut Connection, reader: &mut SocketReader) -> Result<OwnedUniqueName> {
-    let msg = Message::method("/org/freedesktop/DBus", "Hello")?
-        .destination("org.freedesktop.DBus")?
-        .interface("org.freedesktop.DBus")?
-        .build(&())?;
-
-    connection.send(&msg)?;
-
-    let serial = msg.primary_header().serial_num();
-    let res = wait_for_response(reader, serial);
-    let body: OwnedUniqueName = res.body().deserialize()?;
-    Ok(body)
-}
-
 fn open_file(
     connection: &mut Connection,
     reader: &mut SocketReader,
@@ -84,10 +72,7 @@ fn open_file(
     builder = builder.interface("org.freedesktop.portal.FileChooser")?;
     let msg = builder.build(&("", "Title", opts))?;
 
-    connection.send(&msg)?;
-
-    let serial = msg.primary_header().serial_num();
-    let res = wait_for_response(reader, serial);
+    let res = connection.call_method(reader, &msg)?;
     let obj_path = res.body();
     let obj_path: OwnedObjectPath = obj_path.deserialize()?;
 
@@ -128,10 +113,9 @@ fn add_response_signal_match(connection: &mut Connection, obj_path: &str) -> Res
 }
 
 fn main() -> Result<()> {
-    let (mut connection, mut reader) = Connection::session()?;
+    let (mut connection, mut reader, unique_name) = Connection::session_with_hello()?;
     slimbus::set_blocking(connection.as_raw_fd(), true);
 
-    let unique_name = hello(&mut connection, &mut reader)?;
     let obj_path = open_file(&mut connection, &mut reader, &unique_name)?;
 
     let response: Response = loop {
@@ -167,13 +151,3 @@ fn main() -> Result<()> {
 
     Ok(())
 }
-
-fn wait_for_response(reader: &mut SocketReader, serial: NonZeroU32) -> Message {
-    loop {
-        let msg = reader.read_socket().unwrap();
-        println!("<- {:?}", msg);
-        if msg.header().reply_serial() == Some(serial) {
-            break msg;
-        }
-    }
-}
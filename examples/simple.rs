@@ -1,37 +1,17 @@
 use std::os::fd::AsRawFd;
 
-use slimbus::{names::OwnedUniqueName, zvariant::OwnedValue, Connection, Message, Result};
+use slimbus::{zvariant::OwnedValue, Connection, MatchRule, Result};
 
 const INTERFACE: &str = "org.freedesktop.portal.Settings";
 const DESTINATION: &str = "org.freedesktop.portal.Desktop";
 const PATH: &str = "/org/freedesktop/portal/desktop";
 
 fn main() -> Result<()> {
-    let (mut connection, mut reader) = Connection::session()?;
+    let (mut connection, mut reader, name) = Connection::session_with_hello()?;
+    println!("Unique name: {name}");
 
     slimbus::set_blocking(connection.as_raw_fd(), false);
 
-    {
-        let msg = Message::method("/org/freedesktop/DBus", "Hello")?
-            .destination("org.freedesktop.DBus")?
-            .interface("org.freedesktop.DBus")?
-            .build(&())?;
-
-        connection.send(&msg)?;
-
-        let serial = msg.primary_header().serial_num();
-        let name = loop {
-            slimbus::poll(connection.as_raw_fd(), -1);
-
-            let msg = reader.read_socket().unwrap();
-            println!("Got message: {:?}", msg);
-            if msg.header().reply_serial() == Some(serial) {
-                let body: OwnedUniqueName = msg.body().deserialize()?;
-                break body;
-            }
-        };
-        dbg!(name);
-    }
     //
     // {
     //     let mut builder = Message::method(PATH, "Read")?;
@@ -53,38 +33,18 @@ fn main() -> Result<()> {
     //     }
     // }
     {
-        let mut builder = Message::method("/org/freedesktop/DBus", "AddMatch")?;
-        builder = builder.destination("org.freedesktop.DBus")?;
-        builder = builder.interface("org.freedesktop.DBus")?;
-
-        let params = [
-            "type='signal'",
-            "sender='org.freedesktop.portal.Desktop'",
-            "path='/org/freedesktop/portal/desktop'",
-            "interface='org.freedesktop.portal.Settings'",
-            "member='SettingChanged'",
-            "arg0='org.freedesktop.appearance'",
-            "arg1='color-scheme'",
+        let rule = [
+            "type='signal'".to_owned(),
+            format!("sender='{DESTINATION}'"),
+            format!("path='{PATH}'"),
+            format!("interface='{INTERFACE}'"),
+            "member='SettingChanged'".to_owned(),
+            "arg0='org.freedesktop.appearance'".to_owned(),
+            "arg1='color-scheme'".to_owned(),
         ]
         .join(",");
 
-        let msg = builder.build(&params)?;
-
-        let serial = msg.primary_header().serial_num();
-
-        connection.send(&msg)?;
-
-        loop {
-            slimbus::poll(connection.as_raw_fd(), -1);
-
-            let msg = reader.read_socket().unwrap();
-            println!("Got message: {:?}", msg);
-            if msg.header().reply_serial() == Some(serial) {
-                // let body: OwnedValue = msg.body().deserialize()?;
-                // dbg!(body);
-                break;
-            }
-        }
+        connection.add_match(&mut reader, &MatchRule::from(rule))?;
     }
 
     loop {
@@ -102,12 +62,9 @@ fn main() -> Result<()> {
             continue;
         };
 
-        match (interface.as_str(), member.as_str()) {
-            ("org.freedesktop.portal.Settings", "SettingChanged") => {
-                let body: (String, String, OwnedValue) = msg.body().deserialize()?;
-                dbg!(body);
-            }
-            _ => {}
+        if interface == INTERFACE && member == "SettingChanged" {
+            let body: (String, String, OwnedValue) = msg.body().deserialize()?;
+            dbg!(body);
         }
     }
 
@@ -11,20 +11,38 @@
 
 pub mod transport;
 
-use crate::{Error, Guid, OwnedGuid, Result};
-use std::{collections::HashMap, env, str::FromStr};
+use crate::{fdo, Error, Guid, OwnedGuid, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, io,
+    str::FromStr,
+    time::Duration,
+};
 
 use std::fmt::{Display, Formatter};
 
-use self::transport::Stream;
-pub use self::transport::Transport;
+use self::transport::UnixSocket;
+pub use self::transport::{Listener, Stream, Transport};
+
+/// The type of a message bus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BusType {
+    /// The session/user message bus.
+    Session,
+    /// The system-wide message bus.
+    System,
+}
 
 /// A bus address
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Two addresses are equal if they have the same transport, GUID and unknown options, see
+/// [`Address::options`].
+#[derive(Clone, Debug, Eq)]
 #[non_exhaustive]
 pub struct Address {
     guid: Option<OwnedGuid>,
     transport: Transport,
+    options: HashMap<String, String>,
 }
 
 impl Address {
@@ -33,6 +51,7 @@ impl Address {
         Self {
             transport,
             guid: None,
+            options: HashMap::new(),
         }
     }
 
@@ -42,7 +61,9 @@ impl Address {
         G: TryInto<OwnedGuid>,
         G::Error: Into<crate::Error>,
     {
-        self.guid = Some(guid.try_into().map_err(Into::into)?);
+        let guid = guid.try_into().map_err(Into::into)?;
+        self.options.insert("guid".to_owned(), guid.to_string());
+        self.guid = Some(guid);
 
         Ok(self)
     }
@@ -52,34 +73,288 @@ impl Address {
         &self.transport
     }
 
-    pub(crate) fn connect(self) -> Result<Stream> {
+    /// The type of bus this address is for, as a best guess.
+    ///
+    /// This is [`BusType::System`] if the address is the one of the system bus, i.e. its
+    /// well-known socket path or one of the addresses set in the `DBUS_SYSTEM_BUS_ADDRESS`
+    /// environment variable, and [`BusType::Session`] if it's one of the addresses of the session
+    /// bus, see [`Address::session_all`]. Any other address, e.g. a peer-to-peer one, gives `None`.
+    ///
+    /// ```
+    /// use slimbus::address::{Address, BusType};
+    /// use std::str::FromStr;
+    ///
+    /// std::env::set_var("DBUS_SESSION_BUS_ADDRESS", "unix:path=/run/user/1000/bus");
+    /// let system = Address::from_str("unix:path=/run/dbus/system_bus_socket").unwrap();
+    /// assert_eq!(system.bus_type(), Some(BusType::System));
+    /// let session = Address::from_str("unix:path=/run/user/1000/bus").unwrap();
+    /// assert_eq!(session.bus_type(), Some(BusType::Session));
+    /// let p2p = Address::from_str("unix:path=/tmp/example-p2p").unwrap();
+    /// assert_eq!(p2p.bus_type(), None);
+    /// ```
+    pub fn bus_type(&self) -> Option<BusType> {
+        const SYSTEM_BUS_PATHS: [&str; 2] = [
+            "/var/run/dbus/system_bus_socket",
+            "/run/dbus/system_bus_socket",
+        ];
+
+        let is_system_path = match &self.transport {
+            Transport::Unix(unix) => match unix.path() {
+                UnixSocket::File(path) => SYSTEM_BUS_PATHS.iter().any(|p| path.as_os_str() == *p),
+                _ => false,
+            },
+            _ => false,
+        };
+        let is_listed = |addresses: Result<Vec<Self>>| {
+            addresses.is_ok_and(|addresses| addresses.iter().any(|a| a.transport == self.transport))
+        };
+
+        if is_system_path || is_listed(Self::system_all()) {
+            Some(BusType::System)
+        } else if is_listed(Self::session_all()) {
+            Some(BusType::Session)
+        } else {
+            None
+        }
+    }
+
+    /// Connect to this address, blocking until the connection is established.
+    ///
+    /// The D-Bus handshake remains to be done, see [`crate::connection::build_from_stream`].
+    ///
+    /// For `nonce-tcp:` addresses, the nonce is read from the `noncefile` and sent right after
+    /// connecting, so the handshake can follow as usual. A nonce file that can't be read or doesn't
+    /// hold exactly 16 bytes results in [`Error::Address`], before connecting.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{Address, Error};
+    /// # (|| -> slimbus::Result<()> {
+    /// let nonce_file = std::env::temp_dir().join(format!("slimbus-nonce-{}", std::process::id()));
+    /// std::fs::write(&nonce_file, b"too short")?;
+    /// let address = format!("nonce-tcp:noncefile={},host=localhost,port=1", nonce_file.display());
+    ///
+    /// let err = Address::try_from(address.as_str())?.connect().unwrap_err();
+    /// assert!(matches!(err, Error::Address(_)));
+    /// # std::fs::remove_file(nonce_file)?;
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn connect(self) -> Result<Stream> {
         self.transport.connect()
     }
 
+    pub(crate) fn connect_with_retry(self, policy: RetryPolicy) -> Result<Stream> {
+        fn not_accepting(e: &io::Error) -> bool {
+            matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionRefused | io::ErrorKind::NotFound
+            )
+        }
+
+        let mut backoff = policy.backoff;
+        for _ in 0..policy.max_attempts {
+            match self.clone().connect() {
+                Err(Error::InputOutput(e)) if not_accepting(&e) => {
+                    std::thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                }
+                res => return res,
+            }
+        }
+
+        match self.connect() {
+            Err(Error::InputOutput(e)) if policy.max_attempts > 0 && not_accepting(&e) => {
+                Err(fdo::Error::NoServer(e.to_string()).into())
+            }
+            res => res,
+        }
+    }
+
+    /// Start connecting to this address without blocking.
+    ///
+    /// The returned stream is in nonblocking mode and the connection may still be in progress. Wait
+    /// for the socket to become writable to know when it completes and check `SO_ERROR` for the
+    /// outcome. Note that the D-Bus handshake performed by [`crate::connection::build_from_stream`]
+    /// expects a blocking socket, so switch it back with [`crate::set_blocking`] first.
+    ///
+    /// Only the first resolved address is tried for `tcp:` addresses, and `nonce-tcp:` addresses
+    /// result in [`Error::Unsupported`] since the nonce can only be sent once connected.
+    pub fn connect_nonblocking(self) -> Result<Stream> {
+        self.transport.connect_nonblocking()
+    }
+
+    /// Listen on this address, for the server side of connections.
+    ///
+    /// The returned address is the one the listener is actually bound to, for the server to
+    /// advertise to its clients. If this address has no GUID, a new one is generated for it. See
+    /// [`Transport::listen`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{address::transport::{Transport, UnixSocket}, Address};
+    /// # (|| -> slimbus::Result<()> {
+    /// let dir = std::env::temp_dir();
+    /// let (_listener, address) = Address::try_from(format!("unix:dir={}", dir.display()).as_str())?
+    ///     .listen()?;
+    ///
+    /// let Transport::Unix(unix) = address.transport() else { unreachable!() };
+    /// let UnixSocket::File(path) = unix.path() else { unreachable!() };
+    /// assert!(path.file_name().unwrap().to_str().unwrap().starts_with("dbus-"));
+    /// assert!(address.guid().is_some());
+    /// # std::fs::remove_file(path)?;
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn listen(self) -> Result<(Listener, Address)> {
+        let (listener, transport) = self.transport.listen()?;
+        let guid = self.guid.unwrap_or_else(|| Guid::generate().into());
+
+        Ok((listener, Self::new(transport).set_guid(guid)?))
+    }
+
     /// Get the address for session socket respecting the DBUS_SESSION_BUS_ADDRESS environment
     /// variable. If we don't recognize the value (or it's not set) we fall back to
     /// $XDG_RUNTIME_DIR/bus
+    ///
+    /// With the `x11` feature, if the environment variable is not set, the address is first
+    /// looked up in the session file of the X11 display (see [`Address::from_session_file`]).
+    /// The `_DBUS_SESSION_BUS_ADDRESS` property of the root window is not read, since that
+    /// requires an X11 connection.
+    ///
+    /// If the variable holds a list of addresses, this is the first one that parses. See
+    /// [`Address::session_all`] for all of them.
     pub fn session() -> Result<Self> {
+        Self::session_all().map(|mut addresses| addresses.swap_remove(0))
+    }
+
+    /// Get all the addresses for the session socket, in order of preference.
+    ///
+    /// This is like [`Address::session`], but keeps all the addresses listed in the
+    /// `DBUS_SESSION_BUS_ADDRESS` environment variable, see [`Address::parse_all`]. The returned
+    /// list is never empty.
+    pub fn session_all() -> Result<Vec<Self>> {
         match env::var("DBUS_SESSION_BUS_ADDRESS") {
-            Ok(val) => Self::from_str(&val),
+            Ok(val) => Self::parse_all(&val),
             _ => {
-                let id = unsafe { nix::libc::geteuid() }.to_string();
+                #[cfg(feature = "x11")]
+                if let Some(address) = Self::from_session_file()? {
+                    return Ok(vec![address]);
+                }
+
+                let id = crate::utils::euid().to_string();
                 let runtime_dir =
                     env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| format!("/run/user/{}", id));
                 let path = format!("unix:path={runtime_dir}/bus");
 
-                Self::from_str(&path)
+                Self::from_str(&path).map(|address| vec![address])
             }
         }
     }
 
+    /// Read the session bus address from the session file written by `dbus-launch`.
+    ///
+    /// The file is `~/.dbus/session-bus/<machine-id>-<display number>`, with the display number
+    /// taken from the `DISPLAY` environment variable, and assigns the address to
+    /// `DBUS_SESSION_BUS_ADDRESS`. Returns `None` if `DISPLAY` is not set or the file doesn't
+    /// exist, and an error if the file doesn't contain a valid address.
+    pub fn from_session_file() -> Result<Option<Self>> {
+        // The display number is between the host name and the screen number, e.g. `host:1.0`.
+        let Some(display) = env::var("DISPLAY").ok() else {
+            return Ok(None);
+        };
+        let Some((_, display)) = display.rsplit_once(':') else {
+            return Ok(None);
+        };
+        let display = display
+            .split_once('.')
+            .map_or(display, |(number, _)| number);
+        let (Some(home), Ok(machine_id)) = (crate::utils::home_dir(), crate::utils::machine_id())
+        else {
+            return Ok(None);
+        };
+        let path = home
+            .join(".dbus/session-bus")
+            .join(format!("{machine_id}-{display}"));
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // The file is a shell script assigning variables, with the values possibly quoted.
+        let value = contents
+            .lines()
+            .find_map(|line| line.strip_prefix("DBUS_SESSION_BUS_ADDRESS="))
+            .ok_or_else(|| {
+                Error::Address(format!(
+                    "no DBUS_SESSION_BUS_ADDRESS in `{}`",
+                    path.display()
+                ))
+            })?;
+        let value = value.trim().trim_matches(|c| c == '\'' || c == '"');
+
+        Self::from_str(value).map(Some)
+    }
+
     /// Get the address for system bus respecting the DBUS_SYSTEM_BUS_ADDRESS environment
     /// variable. If we don't recognize the value (or it's not set) we fall back to
     /// /var/run/dbus/system_bus_socket
+    ///
+    /// If the variable holds a list of addresses, this is the first one that parses. See
+    /// [`Address::system_all`] for all of them.
     pub fn system() -> Result<Self> {
+        Self::system_all().map(|mut addresses| addresses.swap_remove(0))
+    }
+
+    /// Get all the addresses for the system bus, in order of preference.
+    ///
+    /// This is like [`Address::system`], but keeps all the addresses listed in the
+    /// `DBUS_SYSTEM_BUS_ADDRESS` environment variable, see [`Address::parse_all`]. The returned
+    /// list is never empty.
+    pub fn system_all() -> Result<Vec<Self>> {
         match env::var("DBUS_SYSTEM_BUS_ADDRESS") {
-            Ok(val) => Self::from_str(&val),
-            _ => Self::from_str("unix:path=/var/run/dbus/system_bus_socket"),
+            Ok(val) => Self::parse_all(&val),
+            _ => Self::from_str("unix:path=/var/run/dbus/system_bus_socket")
+                .map(|address| vec![address]),
+        }
+    }
+
+    /// Parse a list of addresses separated by semicolons, as found in the environment variables
+    /// of the buses.
+    ///
+    /// The addresses are fallbacks for each other, in order of preference. Empty entries are
+    /// skipped, as are entries that fail to parse, so that a single malformed or unsupported
+    /// address doesn't prevent using the others. An error is only returned if no address could be
+    /// parsed, which is the error of the last malformed entry if there is one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::Address;
+    /// # (|| -> slimbus::Result<()> {
+    /// let addresses = Address::parse_all("unix:path=/run/bus;;bogus;tcp:host=localhost,port=4142")?;
+    ///
+    /// assert_eq!(addresses.len(), 2);
+    /// assert_eq!(addresses[0], Address::try_from("unix:path=/run/bus")?);
+    /// assert_eq!(addresses[1], Address::try_from("tcp:host=localhost,port=4142")?);
+    /// assert!(Address::parse_all(";bogus;").is_err());
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn parse_all(addresses: &str) -> Result<Vec<Self>> {
+        let mut parsed = Vec::new();
+        let mut last_error = None;
+        for address in addresses.split(';').filter(|a| !a.is_empty()) {
+            match Self::parse_one(address) {
+                Ok(address) => parsed.push(address),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        match last_error {
+            Some(e) if parsed.is_empty() => Err(e),
+            _ if parsed.is_empty() => Err(Error::Address("address list is empty".to_owned())),
+            _ => Ok(parsed),
         }
     }
 
@@ -87,8 +362,124 @@ impl Address {
     pub fn guid(&self) -> Option<&Guid<'_>> {
         self.guid.as_ref().map(|guid| guid.inner())
     }
+
+    /// The value of the option `key` of this address, as parsed from the address string.
+    ///
+    /// Unlike the transport details, this gives access to options slimbus doesn't know about, such
+    /// as the metadata of a container instance. For addresses not parsed from a string, only the
+    /// GUID is available here.
+    ///
+    /// The value is percent-decoded, with any byte sequence that isn't valid UTF-8 replaced by
+    /// `U+FFFD`. The transport details keep the exact bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::Address;
+    /// # (|| -> slimbus::Result<()> {
+    /// let address = Address::try_from("unix:path=/tmp/my%20socket,x-instance=4f1d")?;
+    ///
+    /// assert_eq!(address.option("x-instance"), Some("4f1d"));
+    /// assert_eq!(address.option("path"), Some("/tmp/my socket"));
+    /// assert_eq!(address.option("guid"), None);
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    /// All the options of this address, as parsed from the address string.
+    ///
+    /// This includes the options understood by the transport and the GUID, as well as any unknown
+    /// one, which are kept rather than rejected so future or vendor-specific options can be read.
+    /// See [`Address::option`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::Address;
+    /// # (|| -> slimbus::Result<()> {
+    /// let address = Address::try_from("tcp:host=localhost,port=4142,x-vendor=1")?;
+    /// let mut keys: Vec<_> = address.options().keys().map(String::as_str).collect();
+    /// keys.sort_unstable();
+    ///
+    /// assert_eq!(keys, ["host", "port", "x-vendor"]);
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn options(&self) -> &HashMap<String, String> {
+        &self.options
+    }
+}
+
+/// How to retry connecting to a bus that is not accepting connections yet.
+///
+/// During startup, the socket of a bus may already exist before the daemon accepts connections on
+/// it, in which case connecting fails with `ECONNREFUSED` (or `ENOENT` if the socket is not there
+/// yet). With a retry policy, connecting is retried after waiting for the backoff duration, which
+/// doubles after every attempt. Once all the retries are exhausted, the error is reported as
+/// [`fdo::Error::NoServer`].
+///
+/// The default policy does not retry at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Create a new `RetryPolicy`, retrying up to `max_attempts` times, waiting `backoff` before
+    /// the first retry.
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+        }
+    }
+
+    /// The maximum number of retries.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The duration to wait before the first retry.
+    pub fn backoff(&self) -> Duration {
+        self.backoff
+    }
+}
+
+impl PartialEq for Address {
+    fn eq(&self, other: &Self) -> bool {
+        self.transport == other.transport
+            && self.guid == other.guid
+            && self.unknown_options() == other.unknown_options()
+    }
 }
 
+/// Format the address in its string form, with the values percent-encoded.
+///
+/// This includes the GUID and the unknown options, so that the result parses back into an equal
+/// address. It can thus be logged or passed to a child process, e.g. in the
+/// `DBUS_SESSION_BUS_ADDRESS` environment variable.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::Address;
+/// # use std::str::FromStr;
+/// # (|| -> slimbus::Result<()> {
+/// for s in [
+///     "unix:path=/tmp/my%20socket,guid=0123456789abcdef0123456789abcdef,x-instance=4f1d",
+///     "unix:abstract=/tmp/dbus-Ab%2c12",
+///     "unix:tmpdir=/tmp",
+///     "nonce-tcp:noncefile=/tmp/nonce,host=localhost,port=4142,family=ipv4",
+/// ] {
+///     let address = Address::from_str(s)?;
+///
+///     assert_eq!(address.to_string(), s);
+///     assert_eq!(Address::from_str(&address.to_string())?, address);
+/// }
+/// # Ok(()) })().unwrap()
+/// ```
 impl Display for Address {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         self.transport.fmt(f)?;
@@ -97,15 +488,27 @@ impl Display for Address {
             write!(f, ",guid={}", guid)?;
         }
 
+        for (key, value) in self.unknown_options() {
+            write!(f, ",{key}=")?;
+            transport::encode_percents(f, value.as_bytes())?;
+        }
+
         Ok(())
     }
 }
 
-impl FromStr for Address {
-    type Err = Error;
+impl Address {
+    // The options not described by the transport or the GUID, sorted by key.
+    fn unknown_options(&self) -> BTreeMap<&str, &str> {
+        self.options
+            .iter()
+            .filter(|(k, _)| *k != "guid" && !self.transport.has_option(k))
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
 
-    /// Parse the transport part of a D-Bus address into a `Transport`.
-    fn from_str(address: &str) -> Result<Self> {
+    // Parse a single address, i.e. an entry of an address list.
+    fn parse_one(address: &str) -> Result<Self> {
         let col = address
             .find(':')
             .ok_or_else(|| Error::Address("address has no colon".to_owned()))?;
@@ -122,7 +525,7 @@ impl FromStr for Address {
                         ))
                     }
                 };
-                if options.insert(k, v).is_some() {
+                if options.insert(k, transport::decode_percents(v)?).is_some() {
                     return Err(Error::Address(format!(
                         "Key `{k}` specified multiple times"
                     )));
@@ -130,16 +533,61 @@ impl FromStr for Address {
             }
         }
 
+        let all_options = options
+            .iter()
+            .map(|(k, v)| (k.to_string(), String::from_utf8_lossy(v).into_owned()))
+            .collect();
+
         Ok(Self {
             guid: options
                 .remove("guid")
-                .map(|s| Guid::from_str(s).map(|guid| OwnedGuid::from(guid).to_owned()))
+                .map(|s| {
+                    let s = std::str::from_utf8(&s)
+                        .map_err(|_| Error::Address("`guid` is not valid UTF-8".to_owned()))?;
+                    Guid::from_str(s).map(|guid| OwnedGuid::from(guid).to_owned())
+                })
                 .transpose()?,
             transport: Transport::from_options(transport, options)?,
+            options: all_options,
         })
     }
 }
 
+impl FromStr for Address {
+    type Err = Error;
+
+    /// Parse a D-Bus address.
+    ///
+    /// The values are percent-decoded, an incomplete `%` escape, an invalid hexadecimal digit or
+    /// an unescaped byte outside the optionally-escaped ones resulting in [`Error::Address`].
+    ///
+    /// If `address` is a list of addresses separated by semicolons, this is the first one that
+    /// parses. See [`Address::parse_all`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::address::{transport::{Transport, UnixSocket}, Address};
+    /// # use std::{path::Path, str::FromStr};
+    /// # (|| -> slimbus::Result<()> {
+    /// let address = Address::from_str("unix:path=/tmp/my%20socket")?;
+    ///
+    /// let Transport::Unix(unix) = address.transport() else { unreachable!() };
+    /// assert_eq!(unix.path(), &UnixSocket::File(Path::new("/tmp/my socket").into()));
+    /// assert!(Address::from_str("unix:path=/tmp/my%2").is_err());
+    /// assert!(Address::from_str("unix:path=/tmp/my%zz").is_err());
+    /// assert!(Address::from_str("unix:path=/tmp/my socket").is_err());
+    /// # Ok(()) })().unwrap()
+    /// ```
+    fn from_str(address: &str) -> Result<Self> {
+        if address.contains(';') {
+            Self::parse_all(address).map(|mut addresses| addresses.swap_remove(0))
+        } else {
+            Self::parse_one(address)
+        }
+    }
+}
+
 impl TryFrom<&str> for Address {
     type Error = Error;
 
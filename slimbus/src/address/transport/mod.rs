@@ -4,8 +4,14 @@
 
 use crate::{Error, Result};
 use std::collections::HashMap;
-use std::net::TcpStream;
-use std::os::unix::net::{SocketAddr, UnixStream};
+use std::net::{TcpListener, TcpStream};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::net::{SocketAddr, UnixListener, UnixStream};
+
+use nix::{
+    errno::Errno,
+    sys::socket::{self, AddressFamily, SockFlag, SockType, SockaddrLike, UnixAddr},
+};
 
 use std::{
     fmt::{Display, Formatter},
@@ -55,14 +61,8 @@ impl Transport {
 
             Transport::Tcp(mut addr) => match addr.take_nonce_file() {
                 Some(nonce_file) => {
+                    let nonce = read_nonce(&nonce_file)?;
                     let mut stream = addr.connect()?;
-
-                    let nonce_file = {
-                        use std::os::unix::ffi::OsStrExt;
-                        std::ffi::OsStr::from_bytes(&nonce_file)
-                    };
-
-                    let nonce = std::fs::read(nonce_file)?;
                     std::io::Write::write_all(&mut stream, &nonce)?;
 
                     Ok(Stream::Tcp(stream))
@@ -72,8 +72,126 @@ impl Transport {
         }
     }
 
+    pub(super) fn connect_nonblocking(self) -> Result<Stream> {
+        match self {
+            Transport::Unix(unix) => {
+                let addr = match unix.take_path() {
+                    UnixSocket::File(path) => UnixAddr::new(&path)?,
+                    #[cfg(target_os = "linux")]
+                    UnixSocket::Abstract(name) => UnixAddr::new_abstract(name.as_encoded_bytes())?,
+                    UnixSocket::Dir(_) | UnixSocket::TmpDir(_) => {
+                        // you can't connect to a unix:dir
+                        return Err(Error::Unsupported);
+                    }
+                };
+
+                connect_nonblocking(AddressFamily::Unix, &addr).map(Stream::Unix)
+            }
+
+            Transport::Tcp(addr) => {
+                // The nonce has to be written once the connection is established, which we can't
+                // wait for here.
+                if addr.nonce_file().is_some() {
+                    return Err(Error::Unsupported);
+                }
+
+                let nodelay = addr.nodelay();
+                let addr = addr
+                    .socket_addrs()?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::Address("Failed to connect".into()))?;
+                let stream: TcpStream = match addr {
+                    std::net::SocketAddr::V4(addr) => {
+                        let addr = socket::SockaddrIn::from(addr);
+                        connect_nonblocking(AddressFamily::Inet, &addr)?
+                    }
+                    std::net::SocketAddr::V6(addr) => {
+                        let addr = socket::SockaddrIn6::from(addr);
+                        connect_nonblocking(AddressFamily::Inet6, &addr)?
+                    }
+                };
+                stream.set_nodelay(nodelay)?;
+
+                Ok(Stream::Tcp(stream))
+            }
+        }
+    }
+
+    /// Bind a listening socket to this transport, for the server side of connections.
+    ///
+    /// For `dir=` and `tmpdir=` addresses, a socket with a random name starting with `dbus-` is
+    /// created in the directory. On Linux, `tmpdir=` addresses get an abstract socket of that name
+    /// instead of a file. The returned transport is the one the listener is actually bound to, for
+    /// the server to advertise to its clients.
+    ///
+    /// For `tcp:` addresses, the listener is bound to the first resolved address that can be bound
+    /// and the returned transport carries the actual port, so port `0` can be used to let the
+    /// system pick one. Listening on `nonce-tcp:` addresses is not supported yet and results in
+    /// [`Error::Unsupported`].
+    pub fn listen(self) -> Result<(Listener, Transport)> {
+        match self {
+            Transport::Unix(unix) => {
+                let (path, addr) = match unix.take_path() {
+                    UnixSocket::Dir(dir) => {
+                        let path = dir.join(random_socket_name());
+                        let addr = SocketAddr::from_pathname(&path)?;
+
+                        (UnixSocket::File(path), addr)
+                    }
+                    #[cfg(target_os = "linux")]
+                    UnixSocket::TmpDir(dir) => {
+                        let name = dir.join(random_socket_name()).into_os_string();
+                        let addr = SocketAddr::from_abstract_name(name.as_encoded_bytes())?;
+
+                        (UnixSocket::Abstract(name), addr)
+                    }
+                    #[cfg(not(target_os = "linux"))]
+                    UnixSocket::TmpDir(dir) => {
+                        let path = dir.join(random_socket_name());
+                        let addr = SocketAddr::from_pathname(&path)?;
+
+                        (UnixSocket::File(path), addr)
+                    }
+                    UnixSocket::File(path) => {
+                        let addr = SocketAddr::from_pathname(&path)?;
+
+                        (UnixSocket::File(path), addr)
+                    }
+                    #[cfg(target_os = "linux")]
+                    UnixSocket::Abstract(name) => {
+                        let addr = SocketAddr::from_abstract_name(name.as_encoded_bytes())?;
+
+                        (UnixSocket::Abstract(name), addr)
+                    }
+                };
+                let listener = UnixListener::bind_addr(&addr)?;
+
+                Ok((Listener::Unix(listener), Transport::Unix(Unix::new(path))))
+            }
+            Transport::Tcp(tcp) => {
+                if tcp.nonce_file().is_some() {
+                    return Err(Error::Unsupported);
+                }
+
+                let listener = tcp.listen()?;
+                let port = listener.local_addr()?.port();
+
+                Ok((Listener::Tcp(listener), Transport::Tcp(tcp.set_port(port))))
+            }
+        }
+    }
+
+    // Whether `key` is an option of this transport, which its `Display` impl writes back.
+    pub(super) fn has_option(&self, key: &str) -> bool {
+        match self {
+            Transport::Unix(_) => matches!(key, "path" | "abstract" | "dir" | "tmpdir"),
+            Transport::Tcp(_) => matches!(key, "host" | "port" | "bind" | "family" | "noncefile"),
+        }
+    }
+
     // Helper for `FromStr` impl of `Address`.
-    pub(super) fn from_options(transport: &str, options: HashMap<&str, &str>) -> Result<Self> {
+    pub(super) fn from_options(transport: &str, options: HashMap<&str, Vec<u8>>) -> Result<Self> {
         match transport {
             "unix" => Unix::from_options(options).map(Self::Unix),
             "tcp" => Tcp::from_options(options, false).map(Self::Tcp),
@@ -85,12 +203,140 @@ impl Transport {
     }
 }
 
+/// A stream connected (or connecting) to the address of a [`Transport`].
 #[derive(Debug)]
-pub(crate) enum Stream {
+#[non_exhaustive]
+pub enum Stream {
+    /// A Unix Domain Socket stream.
     Unix(UnixStream),
+    /// A TCP stream.
     Tcp(TcpStream),
 }
 
+impl AsRawFd for Stream {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Stream::Unix(stream) => stream.as_raw_fd(),
+            Stream::Tcp(stream) => stream.as_raw_fd(),
+        }
+    }
+}
+
+/// A socket listening on the address of a [`Transport`].
+///
+/// This is created by [`Transport::listen`] or [`crate::Address::listen`]. Use
+/// [`Listener::accept`] to accept the connections of clients, or poll its file descriptor first to
+/// integrate it into an event loop.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Listener {
+    /// A Unix Domain Socket listener.
+    Unix(UnixListener),
+    /// A TCP listener.
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    /// Accept a new connection, blocking until a client connects.
+    ///
+    /// The returned stream is in blocking mode and ready for the server side of the D-Bus
+    /// handshake. `TCP_NODELAY` is set on TCP streams, as for client connections by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::Address;
+    /// # (|| -> slimbus::Result<()> {
+    /// let (listener, address) = Address::try_from("tcp:host=127.0.0.1,port=0")?.listen()?;
+    /// let client = std::thread::spawn(move || address.connect());
+    ///
+    /// let _server_stream = listener.accept()?;
+    /// let _client_stream = client.join().unwrap()?;
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn accept(&self) -> Result<Stream> {
+        match self {
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+
+                Ok(Stream::Unix(stream))
+            }
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                stream.set_nodelay(true)?;
+
+                Ok(Stream::Tcp(stream))
+            }
+        }
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Listener::Unix(listener) => listener.as_raw_fd(),
+            Listener::Tcp(listener) => listener.as_raw_fd(),
+        }
+    }
+}
+
+// A random socket name for `dir=` and `tmpdir=` addresses, in the same form as the reference
+// implementation.
+fn random_socket_name() -> String {
+    use rand::{distributions::Alphanumeric, thread_rng, Rng};
+
+    let name: String = thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect();
+
+    format!("dbus-{name}")
+}
+
+// Start connecting a new nonblocking socket to `addr`, without waiting for the connection to be
+// established.
+fn connect_nonblocking<S>(family: AddressFamily, addr: &dyn SockaddrLike) -> Result<S>
+where
+    S: From<std::os::fd::OwnedFd>,
+{
+    let fd = socket::socket(
+        family,
+        SockType::Stream,
+        SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+    match socket::connect(fd.as_raw_fd(), addr) {
+        Ok(()) | Err(Errno::EINPROGRESS) => Ok(S::from(fd)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Read the 16-byte nonce of a `nonce-tcp:` address from `nonce_file`, which the client sends first
+// thing after connecting, before the handshake.
+fn read_nonce(nonce_file: &[u8]) -> Result<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::path::Path::new(std::ffi::OsStr::from_bytes(nonce_file));
+    let nonce = std::fs::read(path).map_err(|e| {
+        Error::Address(format!(
+            "failed to read noncefile `{}`: {e}",
+            path.display()
+        ))
+    })?;
+    if nonce.len() != 16 {
+        return Err(Error::Address(format!(
+            "noncefile `{}` holds {} bytes rather than 16",
+            path.display(),
+            nonce.len()
+        )));
+    }
+
+    Ok(nonce)
+}
+
 fn decode_hex(c: char) -> Result<u8> {
     match c {
         '0'..='9' => Ok(c as u8 - b'0'),
@@ -128,7 +374,7 @@ pub(crate) fn decode_percents(value: &str) -> Result<Vec<u8>> {
     Ok(decoded)
 }
 
-pub(super) fn encode_percents(f: &mut Formatter<'_>, mut value: &[u8]) -> std::fmt::Result {
+pub(crate) fn encode_percents(f: &mut Formatter<'_>, mut value: &[u8]) -> std::fmt::Result {
     const LOOKUP: &str = "\
 %00%01%02%03%04%05%06%07%08%09%0a%0b%0c%0d%0e%0f\
 %10%11%12%13%14%15%16%17%18%19%1a%1b%1c%1d%1e%1f\
@@ -1,6 +1,6 @@
 use super::encode_percents;
 use crate::{Error, Result};
-use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
 use std::{
     collections::HashMap,
     fmt::{Display, Formatter},
@@ -15,6 +15,7 @@ pub struct Tcp {
     pub(super) port: u16,
     pub(super) family: Option<TcpTransportFamily>,
     pub(super) nonce_file: Option<Vec<u8>>,
+    pub(super) nodelay: bool,
 }
 
 impl Tcp {
@@ -26,6 +27,7 @@ impl Tcp {
             bind: None,
             family: None,
             nonce_file: None,
+            nodelay: true,
         }
     }
 
@@ -50,6 +52,23 @@ impl Tcp {
         self
     }
 
+    /// Set the `tcp:` address `port` value.
+    pub fn set_port(mut self, port: u16) -> Self {
+        self.port = port;
+
+        self
+    }
+
+    /// Set whether to disable Nagle's algorithm (`TCP_NODELAY`) on the connection.
+    ///
+    /// This is enabled by default: D-Bus messages are typically small and exchanged as requests
+    /// and replies, which Nagle's algorithm would delay. This is not part of the address string.
+    pub fn set_nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+
+        self
+    }
+
     /// Returns the `tcp:` address `host` value.
     pub fn host(&self) -> &str {
         &self.host
@@ -75,13 +94,18 @@ impl Tcp {
         self.nonce_file.as_deref()
     }
 
+    /// Whether Nagle's algorithm is disabled (`TCP_NODELAY`) on the connection.
+    pub fn nodelay(&self) -> bool {
+        self.nodelay
+    }
+
     /// Take ownership of the nonce file path, if any.
     pub fn take_nonce_file(&mut self) -> Option<Vec<u8>> {
         self.nonce_file.take()
     }
 
     pub(super) fn from_options(
-        opts: HashMap<&str, &str>,
+        mut opts: HashMap<&str, Vec<u8>>,
         nonce_tcp_required: bool,
     ) -> Result<Self> {
         let bind = None;
@@ -89,24 +113,26 @@ impl Tcp {
             return Err(Error::Address("`bind` isn't yet supported".into()));
         }
 
-        let host = opts
-            .get("host")
+        let text = |key| {
+            opts.get(key)
+                .map(|v| {
+                    std::str::from_utf8(v)
+                        .map_err(|_| Error::Address(format!("tcp `{key}` is not valid UTF-8")))
+                })
+                .transpose()
+        };
+        let host = text("host")?
             .ok_or_else(|| Error::Address("tcp address is missing `host`".into()))?
             .to_string();
-        let port = opts
-            .get("port")
-            .ok_or_else(|| Error::Address("tcp address is missing `port`".into()))?;
+        let port =
+            text("port")?.ok_or_else(|| Error::Address("tcp address is missing `port`".into()))?;
         let port = port
             .parse::<u16>()
             .map_err(|_| Error::Address("invalid tcp `port`".into()))?;
-        let family = opts
-            .get("family")
-            .map(|f| TcpTransportFamily::from_str(f))
-            .transpose()?;
-        let nonce_file = opts
-            .get("noncefile")
-            .map(|f| super::decode_percents(f))
+        let family = text("family")?
+            .map(TcpTransportFamily::from_str)
             .transpose()?;
+        let nonce_file = opts.remove("noncefile");
         if nonce_tcp_required && nonce_file.is_none() {
             return Err(Error::Address(
                 "nonce-tcp address is missing `noncefile`".into(),
@@ -119,10 +145,45 @@ impl Tcp {
             port,
             family,
             nonce_file,
+            nodelay: true,
         })
     }
 
     pub(super) fn connect(self) -> Result<TcpStream> {
+        let addrs = self.socket_addrs()?;
+
+        // we could attempt connections in parallel?
+        let mut last_err = Error::Address("Failed to connect".into());
+        for addr in addrs {
+            match TcpStream::connect(addr) {
+                Ok(stream) => {
+                    stream.set_nodelay(self.nodelay)?;
+
+                    return Ok(stream);
+                }
+                Err(e) => last_err = e.into(),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    pub(super) fn listen(&self) -> Result<TcpListener> {
+        let addrs = self.socket_addrs()?;
+
+        let mut last_err = Error::Address("Failed to listen".into());
+        for addr in addrs {
+            match TcpListener::bind(addr) {
+                Ok(listener) => return Ok(listener),
+                Err(e) => last_err = e.into(),
+            }
+        }
+
+        Err(last_err)
+    }
+
+    // Resolve the socket addresses matching `host`, `port` and `family`.
+    pub(super) fn socket_addrs(&self) -> Result<Vec<SocketAddr>> {
         let addrs: Result<Vec<SocketAddr>> = {
             let addrs = (self.host(), self.port()).to_socket_addrs()?.filter(|a| {
                 if let Some(family) = self.family() {
@@ -138,19 +199,7 @@ impl Tcp {
             Ok(addrs.collect())
         };
 
-        let addrs =
-            addrs.map_err(|e| Error::Address(format!("Failed to receive TCP addresses: {e}")))?;
-
-        // we could attempt connections in parallel?
-        let mut last_err = Error::Address("Failed to connect".into());
-        for addr in addrs {
-            match TcpStream::connect(addr) {
-                Ok(stream) => return Ok(stream),
-                Err(e) => last_err = e.into(),
-            }
-        }
-
-        Err(last_err)
+        addrs.map_err(|e| Error::Address(format!("Failed to receive TCP addresses: {e}")))
     }
 }
 
@@ -1,7 +1,5 @@
-#[cfg(target_os = "linux")]
-use std::ffi::OsString;
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fmt::{Display, Formatter},
     path::PathBuf,
 };
@@ -30,15 +28,19 @@ impl Unix {
         self.path
     }
 
-    pub(super) fn from_options(opts: std::collections::HashMap<&str, &str>) -> crate::Result<Self> {
-        let path = opts.get("path");
-        let abs = opts.get("abstract");
-        let dir = opts.get("dir");
-        let tmpdir = opts.get("tmpdir");
+    pub(super) fn from_options(
+        mut opts: std::collections::HashMap<&str, Vec<u8>>,
+    ) -> crate::Result<Self> {
+        use std::os::unix::ffi::OsStringExt;
+
+        let path = opts.remove("path").map(OsString::from_vec);
+        let abs = opts.remove("abstract").map(OsString::from_vec);
+        let dir = opts.remove("dir").map(OsString::from_vec);
+        let tmpdir = opts.remove("tmpdir").map(OsString::from_vec);
         let path = match (path, abs, dir, tmpdir) {
             (Some(p), None, None, None) => UnixSocket::File(PathBuf::from(p)),
             #[cfg(target_os = "linux")]
-            (None, Some(p), None, None) => UnixSocket::Abstract(OsString::from(p)),
+            (None, Some(p), None, None) => UnixSocket::Abstract(p),
             #[cfg(not(target_os = "linux"))]
             (None, Some(_), None, None) => {
                 return Err(crate::Error::Address(
@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use crate::{
+    address::{self, BusType, RetryPolicy},
+    Address, Error, Result,
+};
+
+use super::{build_authenticated, AuthIdProvider, AuthMechanism, Connection, SocketReader};
+
+/// A builder for [`Connection`]
+///
+/// The options can be combined freely, e.g. to connect to the first reachable of several addresses
+/// with a custom identity and authentication mechanisms:
+///
+/// ```no_run
+/// # use slimbus::{connection, Address, AuthMechanism};
+/// # (|| -> slimbus::Result<()> {
+/// let (conn, _reader) = connection::Builder::system()?
+///     .auth_id("1000")
+///     .mechanisms(AuthMechanism::external_then_anonymous())
+///     .build()?;
+/// # Ok(()) })().unwrap()
+/// ```
+#[derive(Debug)]
+pub struct Builder {
+    addresses: Vec<Address>,
+    bus_type: Option<BusType>,
+    retry_policy: RetryPolicy,
+    auth_id: Option<Box<dyn AuthIdProvider>>,
+    mechanisms: Option<VecDeque<AuthMechanism>>,
+}
+
+impl Builder {
+    /// Create a builder for a connection to `address`.
+    pub fn address(address: Address) -> Self {
+        Self::addresses([address])
+    }
+
+    /// Create a builder for a connection to the first of `addresses` that can be connected to.
+    ///
+    /// The addresses are tried in order, as fallbacks for each other, like the ones parsed by
+    /// [`Address::parse_all`]. Only failing to connect moves on to the next address: once
+    /// connected, an error in the handshake is returned right away. If no address can be connected
+    /// to, the error of the last one is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{connection, Error};
+    /// let err = connection::Builder::addresses([]).build().unwrap_err();
+    ///
+    /// assert!(matches!(err, Error::Address(_)));
+    /// ```
+    pub fn addresses(addresses: impl IntoIterator<Item = Address>) -> Self {
+        Self {
+            addresses: addresses.into_iter().collect(),
+            bus_type: None,
+            retry_policy: RetryPolicy::default(),
+            auth_id: None,
+            mechanisms: None,
+        }
+    }
+
+    /// Create a builder for a connection to the session/user message bus.
+    ///
+    /// All the addresses listed for the bus are tried in turn, see [`Builder::addresses`].
+    pub fn session() -> Result<Self> {
+        let builder = Self::addresses(Address::session_all()?);
+
+        Ok(Self {
+            bus_type: Some(BusType::Session),
+            ..builder
+        })
+    }
+
+    /// Create a builder for a connection to the system-wide message bus.
+    ///
+    /// All the addresses listed for the bus are tried in turn, see [`Builder::addresses`].
+    pub fn system() -> Result<Self> {
+        let builder = Self::addresses(Address::system_all()?);
+
+        Ok(Self {
+            bus_type: Some(BusType::System),
+            ..builder
+        })
+    }
+
+    /// Retry connecting to each address according to `policy`.
+    ///
+    /// See [`RetryPolicy`] for details.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+
+        self
+    }
+
+    /// Authenticate as `auth_id` rather than the effective user ID.
+    ///
+    /// This is for daemons authenticating on behalf of several users. See
+    /// [`ClientHandshakeMachine::set_auth_id`] for the requirements on `auth_id`.
+    ///
+    /// [`ClientHandshakeMachine::set_auth_id`]: super::ClientHandshakeMachine::set_auth_id
+    pub fn auth_id(self, auth_id: impl Into<String>) -> Self {
+        self.auth_id_provider(auth_id.into())
+    }
+
+    /// Authenticate as the identity given by `provider` rather than the effective user ID.
+    ///
+    /// See [`AuthIdProvider`] for details.
+    pub fn auth_id_provider(mut self, provider: impl AuthIdProvider + 'static) -> Self {
+        self.auth_id = Some(Box::new(provider));
+
+        self
+    }
+
+    /// Try the authentication `mechanisms` in order.
+    ///
+    /// A mechanism rejected by the server is followed by the next one. If all of them are
+    /// rejected, [`Error::Handshake`] is returned. By default, all the supported mechanisms are
+    /// tried. See [`AuthMechanism::external_then_anonymous`] for a common choice.
+    pub fn mechanisms(mut self, mechanisms: impl IntoIterator<Item = AuthMechanism>) -> Self {
+        self.mechanisms = Some(mechanisms.into_iter().collect());
+
+        self
+    }
+
+    /// Build the connection, consuming the builder.
+    ///
+    /// # Errors
+    ///
+    /// Until server-side bus connection is supported, attempting to build such a connection will
+    /// result in [`Error::Unsupported`] error.
+    pub fn build(self) -> Result<(Connection, SocketReader)> {
+        let mut last_error = Error::Address("no address to connect to".to_owned());
+        for address in self.addresses {
+            match address.clone().connect_with_retry(self.retry_policy) {
+                Ok(stream) => {
+                    let bus_type = self.bus_type.or_else(|| address.bus_type());
+                    let (mut connection, reader) =
+                        build_connected(address, stream, self.auth_id, self.mechanisms)?;
+                    connection.bus_type = bus_type;
+
+                    return Ok((connection, reader));
+                }
+                Err(e) => last_error = e,
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+// Build the connection over `stream`, connected to `address`.
+fn build_connected(
+    address: Address,
+    stream: address::Stream,
+    auth_id: Option<Box<dyn AuthIdProvider>>,
+    mechanisms: Option<VecDeque<AuthMechanism>>,
+) -> Result<(Connection, SocketReader)> {
+    let server_guid = address.guid().map(|g| g.to_owned().into());
+    let (mut connection, reader) = build_authenticated(stream, server_guid, auth_id, mechanisms)?;
+    connection.address = Some(address);
+
+    Ok((connection, reader))
+}
@@ -8,46 +8,7 @@ use std::{
 };
 use zvariant::Str;
 
-fn home_dir() -> Option<PathBuf> {
-    match std::env::var("HOME") {
-        Ok(home) => Some(home.into()),
-        Err(_) => unix::home_dir(),
-    }
-}
-
-mod unix {
-    use std::ffi::{CStr, OsStr};
-    use std::os::unix::ffi::OsStrExt;
-    use std::path::PathBuf;
-
-    pub(super) fn home_dir() -> Option<PathBuf> {
-        let uid = unsafe { nix::libc::geteuid() };
-        let passwd = unsafe { nix::libc::getpwuid(uid) };
-
-        // getpwnam(3):
-        // The getpwnam() and getpwuid() functions return a pointer to a passwd structure, or NULL
-        // if the matching entry is not found or an error occurs. If an error occurs, errno is set
-        // to indicate the error. If one wants to check errno after the call, it should be set to
-        // zero before the call. The return value may point to a static area, and may be overwritten
-        // by subsequent calls to getpwent(3), getpwnam(), or getpwuid().
-        if passwd.is_null() {
-            return None;
-        }
-
-        // SAFETY: `getpwuid()` returns either NULL or a valid pointer to a `passwd` structure.
-        let passwd = unsafe { &*passwd };
-        if passwd.pw_dir.is_null() {
-            return None;
-        }
-
-        // SAFETY: `getpwuid()->pw_dir` is a valid pointer to a c-string.
-        let home_dir = unsafe { CStr::from_ptr(passwd.pw_dir) };
-
-        Some(PathBuf::from(OsStr::from_bytes(home_dir.to_bytes())))
-    }
-}
-
-use crate::{guid::Guid, Error, OwnedGuid, Result};
+use crate::{guid::Guid, utils::home_dir, Error, OwnedGuid, Result};
 
 use super::socket::{BoxedSplit, ReadHalf, WriteHalf};
 
@@ -70,6 +31,119 @@ pub enum AuthMechanism {
     Anonymous,
 }
 
+impl AuthMechanism {
+    /// The mechanisms to try `EXTERNAL` first, then fall back to `ANONYMOUS` if it is rejected.
+    ///
+    /// This is for best-effort connections to servers that may or may not accept our credentials.
+    /// Note that a connection falling back to `ANONYMOUS` is not authenticated, so the server can't
+    /// apply any credential-based authorization to it, e.g. a bus policy granting access to a
+    /// user. Message buses normally reject `ANONYMOUS` altogether.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use slimbus::{connection, Address, AuthMechanism};
+    /// # (|| -> slimbus::Result<()> {
+    /// let address = "unix:path=/run/example/socket".parse::<Address>()?;
+    /// let (conn, _reader) = connection::Builder::address(address)
+    ///     .mechanisms(AuthMechanism::external_then_anonymous())
+    ///     .build()?;
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn external_then_anonymous() -> Vec<AuthMechanism> {
+        vec![AuthMechanism::External, AuthMechanism::Anonymous]
+    }
+}
+
+/// A source of the identity to authenticate as with the `EXTERNAL` and `DBUS_COOKIE_SHA1`
+/// mechanisms.
+///
+/// The handshake asks for the identity when it starts one of these mechanisms. By default, this is
+/// [`EffectiveUid`]. Another identity is needed e.g. in a user namespace, where the effective user
+/// ID differs from the one the server sees, see [`AuthIdFromEnv`] and [`AuthIdFromFile`]. A
+/// `String` is a fixed identity.
+///
+/// The server only accepts the identity for `EXTERNAL` if it matches the credentials of the
+/// socket.
+pub trait AuthIdProvider: Debug + Send {
+    /// The identity to authenticate as, e.g. the Unix user ID `"1000"`.
+    fn auth_id(&self) -> Result<String>;
+}
+
+/// The effective user ID of the process, the default [`AuthIdProvider`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EffectiveUid;
+
+impl AuthIdProvider for EffectiveUid {
+    fn auth_id(&self) -> Result<String> {
+        Ok(crate::utils::euid().to_string())
+    }
+}
+
+impl AuthIdProvider for String {
+    fn auth_id(&self) -> Result<String> {
+        Ok(self.clone())
+    }
+}
+
+impl<P: AuthIdProvider + ?Sized> AuthIdProvider for Box<P> {
+    fn auth_id(&self) -> Result<String> {
+        (**self).auth_id()
+    }
+}
+
+/// An [`AuthIdProvider`] reading the identity from the environment variable of the given name.
+///
+/// The handshake fails with [`Error::Handshake`] if the variable is unset or not valid Unicode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthIdFromEnv(pub String);
+
+impl AuthIdProvider for AuthIdFromEnv {
+    fn auth_id(&self) -> Result<String> {
+        std::env::var(&self.0)
+            .map_err(|e| Error::Handshake(format!("Failed to read auth ID from `{}`: {e}", self.0)))
+    }
+}
+
+/// An [`AuthIdProvider`] reading the identity from the file at the given path.
+///
+/// Leading and trailing whitespace, such as a final newline, is ignored. The handshake fails with
+/// [`Error::Handshake`] if the file can't be read or is empty.
+///
+/// # Example
+///
+/// ```no_run
+/// # use slimbus::{connection::{self, AuthIdFromFile}, Address};
+/// # (|| -> slimbus::Result<()> {
+/// let provider = AuthIdFromFile("/run/example/host-uid".into());
+/// let (conn, _reader) = connection::Builder::address(Address::session()?)
+///     .auth_id_provider(provider)
+///     .build()?;
+/// # Ok(()) })().unwrap()
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthIdFromFile(pub PathBuf);
+
+impl AuthIdProvider for AuthIdFromFile {
+    fn auth_id(&self) -> Result<String> {
+        let auth_id = std::fs::read_to_string(&self.0).map_err(|e| {
+            Error::Handshake(format!(
+                "Failed to read auth ID from `{}`: {e}",
+                self.0.display()
+            ))
+        })?;
+        let auth_id = auth_id.trim();
+        if auth_id.is_empty() {
+            return Err(Error::Handshake(format!(
+                "No auth ID in `{}`",
+                self.0.display()
+            )));
+        }
+
+        Ok(auth_id.to_owned())
+    }
+}
+
 /// The result of a finalized handshake
 ///
 /// The result of a finalized [`ClientHandshake`] or [`ServerHandshake`]. It can be passed to
@@ -90,12 +164,24 @@ pub struct Authenticated {
 
 impl Authenticated {
     /// Create a client-side `Authenticated` for the given `socket`.
+    ///
+    /// File descriptor passing is only negotiated if `unix_fd` is `true` and the socket supports
+    /// it. `auth_id` is the identity to authenticate as, see [`ClientHandshake::set_auth_id`]. Use
+    /// [`ClientHandshake`] directly to get the identity from an [`AuthIdProvider`] instead.
     pub fn client(
         socket: BoxedSplit,
         server_guid: Option<OwnedGuid>,
         mechanisms: Option<VecDeque<AuthMechanism>>,
+        unix_fd: bool,
+        auth_id: Option<String>,
     ) -> Result<Self> {
-        ClientHandshake::new(socket, mechanisms, server_guid).perform()
+        let mut handshake =
+            ClientHandshake::new(socket, mechanisms, server_guid).set_unix_fd(unix_fd);
+        if let Some(auth_id) = auth_id {
+            handshake = handshake.set_auth_id(auth_id);
+        }
+
+        handshake.perform()
     }
 }
 
@@ -134,22 +220,12 @@ enum Command {
 
 /// A representation of an in-progress handshake, client-side
 ///
-/// This struct is an async-compatible representation of the initial handshake that must be
-/// performed before a D-Bus connection can be used. To use it, you should call the
-/// [`advance_handshake`] method whenever the underlying socket becomes ready (tracking the
-/// readiness itself is not managed by this abstraction) until it returns `Ok(())`, at which point
-/// you can invoke the [`try_finish`] method to get an [`Authenticated`], which can be given to
-/// [`Connection::new_authenticated`].
-///
-/// [`advance_handshake`]: struct.ClientHandshake.html#method.advance_handshake
-/// [`try_finish`]: struct.ClientHandshake.html#method.try_finish
-/// [`Authenticated`]: struct.AUthenticated.html
-/// [`Connection::new_authenticated`]: ../struct.Connection.html#method.new_authenticated
+/// This drives a [`ClientHandshakeMachine`] over the given socket, blocking on reads and writes
+/// until the handshake is complete.
 #[derive(Debug)]
 pub struct ClientHandshake {
-    common: HandshakeCommon,
-    server_guid: Option<OwnedGuid>,
-    step: ClientHandshakeStep,
+    socket: BoxedSplit,
+    machine: ClientHandshakeMachine,
 }
 
 pub trait Handshake {
@@ -167,6 +243,114 @@ impl ClientHandshake {
         mechanisms: Option<VecDeque<AuthMechanism>>,
         server_guid: Option<OwnedGuid>,
     ) -> ClientHandshake {
+        let negotiate_unix_fd = socket.read().can_pass_unix_fd();
+
+        ClientHandshake {
+            socket,
+            machine: ClientHandshakeMachine::new(mechanisms, server_guid, negotiate_unix_fd),
+        }
+    }
+
+    /// Whether to negotiate file descriptor passing, `true` by default.
+    ///
+    /// If `false`, `NEGOTIATE_UNIX_FD` is never sent, even if the socket could pass file
+    /// descriptors, so the resulting connection can neither send nor receive them.
+    pub fn set_unix_fd(mut self, unix_fd: bool) -> Self {
+        self.machine.negotiate_unix_fd = unix_fd && self.socket.read().can_pass_unix_fd();
+
+        self
+    }
+
+    /// The identity to authenticate as, see [`ClientHandshakeMachine::set_auth_id`].
+    pub fn set_auth_id(mut self, auth_id: impl Into<String>) -> Self {
+        self.machine = self.machine.set_auth_id(auth_id);
+
+        self
+    }
+
+    /// The source of the identity to authenticate as, see
+    /// [`ClientHandshakeMachine::set_auth_id_provider`].
+    pub fn set_auth_id_provider(mut self, provider: impl AuthIdProvider + 'static) -> Self {
+        self.machine = self.machine.set_auth_id_provider(provider);
+
+        self
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        while !self.machine.pending_output().is_empty() {
+            let written = self
+                .socket
+                .write_mut()
+                .sendmsg(self.machine.pending_output(), &[])?;
+            self.machine.consume_output(written);
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<()> {
+        let mut buf = [0; 64];
+        let (read, fds) = self.socket.read_mut().recvmsg(&mut buf)?;
+        if !fds.is_empty() {
+            return Err(Error::Handshake("Unexpected FDs during handshake".into()));
+        }
+        if read == 0 {
+            return Err(Error::Handshake("Unexpected EOF during handshake".into()));
+        }
+        self.machine.feed(&buf[..read]);
+
+        Ok(())
+    }
+}
+
+/// The progress of a [`ClientHandshakeMachine`], as returned by
+/// [`ClientHandshakeMachine::advance`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandshakeProgress {
+    /// More input from the server is needed, pass it to [`ClientHandshakeMachine::feed`].
+    NeedInput,
+    /// The handshake is complete, once the pending output has been sent.
+    Done,
+}
+
+/// The client side of the handshake, as a state machine doing no I/O of its own.
+///
+/// Bytes received from the server are passed to [`feed`], and [`advance`] then processes them,
+/// queuing the bytes to send back. These are available from [`pending_output`] and should be
+/// marked as sent with [`consume_output`]. Repeat until [`advance`] returns
+/// [`HandshakeProgress::Done`], and send the remaining output.
+///
+/// The output starts with the nul byte required before the first command. On platforms where it
+/// has to carry credentials, send it accordingly and then consume it.
+///
+/// [`feed`]: ClientHandshakeMachine::feed
+/// [`advance`]: ClientHandshakeMachine::advance
+/// [`pending_output`]: ClientHandshakeMachine::pending_output
+/// [`consume_output`]: ClientHandshakeMachine::consume_output
+#[derive(Debug)]
+pub struct ClientHandshakeMachine {
+    step: ClientHandshakeStep,
+    server_guid: Option<OwnedGuid>,
+    negotiate_unix_fd: bool,
+    cap_unix_fd: bool,
+    auth_id: Box<dyn AuthIdProvider>,
+    // the current AUTH mechanism is front, ordered by priority
+    mechanisms: VecDeque<AuthMechanism>,
+    recv_buffer: Vec<u8>,
+    send_buffer: Vec<u8>,
+}
+
+impl ClientHandshakeMachine {
+    /// Create a new client handshake state machine.
+    ///
+    /// If `mechanisms` is `None`, all the supported mechanisms are tried. If `server_guid` is
+    /// given, the handshake fails if the server reports a different GUID. File descriptor passing
+    /// is only negotiated if `negotiate_unix_fd` is `true`.
+    pub fn new(
+        mechanisms: Option<VecDeque<AuthMechanism>>,
+        server_guid: Option<OwnedGuid>,
+        negotiate_unix_fd: bool,
+    ) -> Self {
         let mechanisms = mechanisms.unwrap_or_else(|| {
             let mut mechanisms = VecDeque::new();
             mechanisms.push_back(AuthMechanism::External);
@@ -175,16 +359,199 @@ impl ClientHandshake {
             mechanisms
         });
 
-        ClientHandshake {
-            common: HandshakeCommon::new(socket, mechanisms),
+        Self {
             step: ClientHandshakeStep::Init,
             server_guid,
+            negotiate_unix_fd,
+            cap_unix_fd: false,
+            auth_id: Box::new(EffectiveUid),
+            mechanisms,
+            recv_buffer: Vec::new(),
+            send_buffer: Vec::new(),
         }
     }
 
+    /// The identity to authenticate as with the `EXTERNAL` and `DBUS_COOKIE_SHA1` mechanisms.
+    ///
+    /// By default, this is the effective user ID of the process. A daemon acting on behalf of
+    /// several users may claim another one, e.g. `"1000"`, but the server only accepts it for
+    /// `EXTERNAL` if it matches the credentials of the socket, e.g. because the socket was
+    /// connected with that effective user ID.
+    pub fn set_auth_id(mut self, auth_id: impl Into<String>) -> Self {
+        self.auth_id = Box::new(auth_id.into());
+
+        self
+    }
+
+    /// The source of the identity to authenticate as with the `EXTERNAL` and `DBUS_COOKIE_SHA1`
+    /// mechanisms, [`EffectiveUid`] by default.
+    ///
+    /// The identity is asked for when one of these mechanisms starts, and an error from `provider`
+    /// fails the handshake.
+    pub fn set_auth_id_provider(mut self, provider: impl AuthIdProvider + 'static) -> Self {
+        self.auth_id = Box::new(provider);
+
+        self
+    }
+
+    /// Pass bytes received from the server.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.recv_buffer.extend_from_slice(bytes);
+    }
+
+    /// The bytes to send to the server.
+    pub fn pending_output(&self) -> &[u8] {
+        &self.send_buffer
+    }
+
+    /// Mark the first `n` bytes of the [pending output] as sent.
+    ///
+    /// [pending output]: ClientHandshakeMachine::pending_output
+    pub fn consume_output(&mut self, n: usize) {
+        self.send_buffer.drain(..n);
+    }
+
+    /// Process the input received so far.
+    pub fn advance(&mut self) -> Result<HandshakeProgress> {
+        use ClientHandshakeStep::*;
+        loop {
+            let (next_step, cmd) = match self.step {
+                Init => {
+                    trace!("Initializing");
+                    self.send_buffer.push(b'\0');
+                    self.mechanism_init()?
+                }
+                MechanismInit => {
+                    trace!("Initializing auth mechanisms");
+                    self.mechanism_init()?
+                }
+                WaitingForData | WaitingForOK => {
+                    trace!("Waiting for DATA or OK from server");
+                    let Some(reply) = self.read_command()? else {
+                        return Ok(HandshakeProgress::NeedInput);
+                    };
+                    match (self.step, reply) {
+                        (_, Command::Data(data)) => {
+                            trace!("Received DATA from server");
+                            let data = data.ok_or_else(|| {
+                                Error::Handshake("Received DATA with no data from server".into())
+                            })?;
+                            self.mechanism_data(data)?
+                        }
+                        (_, Command::Rejected(_)) => {
+                            trace!("Received REJECT from server. Will try next auth mechanism..");
+                            self.mechanisms.pop_front();
+                            self.step = MechanismInit;
+                            continue;
+                        }
+                        (WaitingForOK, Command::Ok(guid)) => {
+                            trace!("Received OK from server");
+                            match &self.server_guid {
+                                Some(server_guid) if *server_guid != guid => {
+                                    return Err(Error::Handshake(format!(
+                                        "Server GUID mismatch: expected {server_guid}, got {guid}",
+                                    )));
+                                }
+                                Some(_) => (),
+                                None => self.server_guid = Some(guid),
+                            }
+                            if self.negotiate_unix_fd {
+                                (WaitingForAgreeUnixFD, Command::NegotiateUnixFD)
+                            } else {
+                                (Done, Command::Begin)
+                            }
+                        }
+                        (_, reply) => {
+                            return Err(Error::Handshake(format!(
+                                "Unexpected server AUTH OK reply: {reply}"
+                            )));
+                        }
+                    }
+                }
+                WaitingForAgreeUnixFD => {
+                    trace!("Waiting for Unix FD passing agreement from server");
+                    let Some(reply) = self.read_command()? else {
+                        return Ok(HandshakeProgress::NeedInput);
+                    };
+                    match reply {
+                        Command::AgreeUnixFD => {
+                            trace!("Unix FD passing agreed by server");
+                            self.cap_unix_fd = true
+                        }
+                        Command::Error(_) => {
+                            trace!("Unix FD passing rejected by server");
+                            self.cap_unix_fd = false
+                        }
+                        _ => {
+                            return Err(Error::Handshake(format!(
+                                "Unexpected server UNIX_FD reply: {reply}"
+                            )));
+                        }
+                    }
+                    (Done, Command::Begin)
+                }
+                Done => {
+                    trace!("Handshake done");
+                    return Ok(HandshakeProgress::Done);
+                }
+            };
+            self.send_buffer.extend(Vec::<u8>::from(cmd));
+            self.step = next_step;
+        }
+    }
+
+    /// Whether the handshake is complete.
+    pub fn is_done(&self) -> bool {
+        self.step == ClientHandshakeStep::Done
+    }
+
+    /// The GUID of the server, once it has been received.
+    pub fn server_guid(&self) -> Option<&OwnedGuid> {
+        self.server_guid.as_ref()
+    }
+
+    /// Whether file descriptor passing has been accepted by both sides.
+    pub fn cap_unix_fd(&self) -> bool {
+        self.cap_unix_fd
+    }
+
+    /// Consume the state machine, returning the bytes received past the end of the handshake.
+    ///
+    /// These are the beginning of the first message from the server.
+    pub fn into_received_bytes(self) -> Vec<u8> {
+        self.recv_buffer
+    }
+
+    // Parse the next command from the received bytes, if a complete line is available.
+    fn read_command(&mut self) -> Result<Option<Command>> {
+        let Some(i) = self.recv_buffer.iter().position(|b| *b == b'\n') else {
+            return Ok(None);
+        };
+        if i == 0 || self.recv_buffer[i - 1] != b'\r' {
+            return Err(Error::Handshake("Invalid line ending in handshake".into()));
+        }
+
+        let line_bytes = self.recv_buffer.drain(..i + 1);
+        let line = std::str::from_utf8(line_bytes.as_slice())
+            .map_err(|e| Error::Handshake(e.to_string()))?;
+
+        trace!("Reading {line}");
+        line.parse().map(Some)
+    }
+
+    fn mechanism(&self) -> Result<&AuthMechanism> {
+        self.mechanisms
+            .front()
+            .ok_or_else(|| Error::Handshake("Exhausted available AUTH mechanisms".into()))
+    }
+
+    fn auth_id(&self) -> Result<Vec<u8>> {
+        self.auth_id.auth_id().map(String::into_bytes)
+    }
+
     fn mechanism_init(&mut self) -> Result<(ClientHandshakeStep, Command)> {
         use ClientHandshakeStep::*;
-        let mech = self.common.mechanism()?;
+        let mech = self.mechanism()?;
         match mech {
             AuthMechanism::Anonymous => Ok((
                 WaitingForOK,
@@ -192,17 +559,17 @@ impl ClientHandshake {
             )),
             AuthMechanism::External => Ok((
                 WaitingForOK,
-                Command::Auth(Some(*mech), Some(sasl_auth_id()?.into_bytes())),
+                Command::Auth(Some(*mech), Some(self.auth_id()?)),
             )),
             AuthMechanism::Cookie => Ok((
                 WaitingForData,
-                Command::Auth(Some(*mech), Some(sasl_auth_id()?.into_bytes())),
+                Command::Auth(Some(*mech), Some(self.auth_id()?)),
             )),
         }
     }
 
     fn mechanism_data(&mut self, data: Vec<u8>) -> Result<(ClientHandshakeStep, Command)> {
-        let mech = self.common.mechanism()?;
+        let mech = self.mechanism()?;
         match mech {
             AuthMechanism::Cookie => {
                 let context = std::str::from_utf8(&data)
@@ -249,11 +616,6 @@ fn random_ascii(len: usize) -> String {
         .collect()
 }
 
-fn sasl_auth_id() -> Result<String> {
-    let id = unsafe { nix::libc::geteuid() }.to_string();
-    Ok(id)
-}
-
 #[derive(Debug)]
 struct Cookie {
     id: usize,
@@ -365,133 +727,42 @@ impl Default for CookieContext<'_> {
 
 impl Handshake for ClientHandshake {
     fn perform(mut self) -> Result<Authenticated> {
-        use ClientHandshakeStep::*;
-        loop {
-            let (next_step, cmd) = match self.step {
-                Init => {
-                    trace!("Initializing");
-                    #[allow(clippy::let_and_return)]
-                    let ret = self.mechanism_init()?;
-                    // The dbus daemon on some platforms requires sending the zero byte as a
-                    // separate message with SCM_CREDS.
-                    #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
-                    let written = self
-                        .common
-                        .socket
-                        .write_mut()
-                        .send_zero_byte()
-                        .map_err(|e| {
-                            Error::Handshake(format!(
-                                "Could not send zero byte with credentials: {}",
-                                e
-                            ))
-                        })
-                        .and_then(|n| match n {
-                            None => Err(Error::Handshake(
-                                "Could not send zero byte with credentials".to_string(),
-                            )),
-                            Some(n) => Ok(n),
-                        })?;
-
-                    // leading 0 is sent separately already for `freebsd` and `dragonfly` above.
-                    #[cfg(not(any(target_os = "freebsd", target_os = "dragonfly")))]
-                    let written = self.common.socket.write_mut().sendmsg(&[b'\0'], &[])?;
-
-                    if written != 1 {
-                        return Err(Error::Handshake(
-                            "Could not send zero byte with credentials".to_string(),
-                        ));
-                    }
+        let mut progress = self.machine.advance()?;
 
-                    ret
-                }
-                MechanismInit => {
-                    trace!("Initializing auth mechanisms");
-                    self.mechanism_init()?
-                }
-                WaitingForData | WaitingForOK => {
-                    trace!("Waiting for DATA or OK from server");
-                    let reply = self.common.read_command()?;
-                    match (self.step, reply) {
-                        (_, Command::Data(data)) => {
-                            trace!("Received DATA from server");
-                            let data = data.ok_or_else(|| {
-                                Error::Handshake("Received DATA with no data from server".into())
-                            })?;
-                            self.mechanism_data(data)?
-                        }
-                        (_, Command::Rejected(_)) => {
-                            trace!("Received REJECT from server. Will try next auth mechanism..");
-                            self.common.mechanisms.pop_front();
-                            self.step = MechanismInit;
-                            continue;
-                        }
-                        (WaitingForOK, Command::Ok(guid)) => {
-                            trace!("Received OK from server");
-                            match self.server_guid {
-                                Some(server_guid) if server_guid != guid => {
-                                    return Err(Error::Handshake(format!(
-                                        "Server GUID mismatch: expected {server_guid}, got {guid}",
-                                    )));
-                                }
-                                Some(_) => (),
-                                None => self.server_guid = Some(guid),
-                            }
-                            if self.common.socket.read_mut().can_pass_unix_fd() {
-                                (WaitingForAgreeUnixFD, Command::NegotiateUnixFD)
-                            } else {
-                                (Done, Command::Begin)
-                            }
-                        }
-                        (_, reply) => {
-                            return Err(Error::Handshake(format!(
-                                "Unexpected server AUTH OK reply: {reply}"
-                            )));
-                        }
-                    }
-                }
-                WaitingForAgreeUnixFD => {
-                    trace!("Waiting for Unix FD passing agreement from server");
-                    let reply = self.common.read_command()?;
-                    match reply {
-                        Command::AgreeUnixFD => {
-                            trace!("Unix FD passing agreed by server");
-                            self.common.cap_unix_fd = true
-                        }
-                        Command::Error(_) => {
-                            trace!("Unix FD passing rejected by server");
-                            self.common.cap_unix_fd = false
-                        }
-                        _ => {
-                            return Err(Error::Handshake(format!(
-                                "Unexpected server UNIX_FD reply: {reply}"
-                            )));
-                        }
-                    }
-                    (Done, Command::Begin)
-                }
-                Done => {
-                    trace!("Handshake done");
-                    let (read, write) = self.common.socket.take();
-                    return Ok(Authenticated {
-                        socket_write: write,
-                        socket_read: Some(read),
-                        cap_unix_fd: self.common.cap_unix_fd,
-                        already_received_bytes: Some(self.common.recv_buffer),
-                    });
-                }
-            };
-            self.common.write_command(cmd)?;
-            self.step = next_step;
+        // The dbus daemon on some platforms requires sending the zero byte as a separate message
+        // with SCM_CREDS.
+        #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+        {
+            let written = self.socket.write_mut().send_zero_byte().map_err(|e| {
+                Error::Handshake(format!("Could not send zero byte with credentials: {}", e))
+            })?;
+            if written != Some(1) {
+                return Err(Error::Handshake(
+                    "Could not send zero byte with credentials".to_string(),
+                ));
+            }
+            self.machine.consume_output(1);
         }
-    }
-}
 
-/*
- * Server-side handshake logic
- */
+        loop {
+            self.flush()?;
+            if progress == HandshakeProgress::Done {
+                break;
+            }
+            self.receive()?;
+            progress = self.machine.advance()?;
+        }
 
-/// A representation of an in-progress handshake, server-side
+        let cap_unix_fd = self.machine.cap_unix_fd();
+        let (read, write) = self.socket.take();
+        Ok(Authenticated {
+            socket_write: write,
+            socket_read: Some(read),
+            cap_unix_fd,
+            already_received_bytes: Some(self.machine.into_received_bytes()),
+        })
+    }
+}
 
 impl fmt::Display for AuthMechanism {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -609,77 +880,3 @@ impl FromStr for Command {
         Ok(cmd)
     }
 }
-
-// Common code for the client and server side of the handshake.
-#[derive(Debug)]
-pub struct HandshakeCommon {
-    socket: BoxedSplit,
-    recv_buffer: Vec<u8>,
-    cap_unix_fd: bool,
-    // the current AUTH mechanism is front, ordered by priority
-    mechanisms: VecDeque<AuthMechanism>,
-}
-
-impl HandshakeCommon {
-    /// Start a handshake on this client socket
-    pub fn new(socket: BoxedSplit, mechanisms: VecDeque<AuthMechanism>) -> Self {
-        Self {
-            socket,
-            recv_buffer: Vec::new(),
-            cap_unix_fd: false,
-            mechanisms,
-        }
-    }
-
-    fn write_command(&mut self, command: Command) -> Result<()> {
-        let mut send_buffer = Vec::<u8>::from(command);
-        while !send_buffer.is_empty() {
-            let written = self.socket.write_mut().sendmsg(&send_buffer, &[])?;
-            send_buffer.drain(..written);
-        }
-        Ok(())
-    }
-
-    fn read_command(&mut self) -> Result<Command> {
-        let mut cmd_end = 0;
-        loop {
-            if let Some(i) = self.recv_buffer[cmd_end..].iter().position(|b| *b == b'\n') {
-                if cmd_end + i == 0 || self.recv_buffer.get(cmd_end + i - 1) != Some(&b'\r') {
-                    return Err(Error::Handshake("Invalid line ending in handshake".into()));
-                }
-                cmd_end += i + 1;
-
-                break;
-            } else {
-                cmd_end = self.recv_buffer.len();
-            }
-
-            let mut buf = [0; 64];
-            let res = self.socket.read_mut().recvmsg(&mut buf)?;
-            let read = {
-                let (read, fds) = res;
-                if !fds.is_empty() {
-                    return Err(Error::Handshake("Unexpected FDs during handshake".into()));
-                }
-                read
-            };
-            if read == 0 {
-                return Err(Error::Handshake("Unexpected EOF during handshake".into()));
-            }
-            self.recv_buffer.extend(&buf[..read]);
-        }
-
-        let line_bytes = self.recv_buffer.drain(..cmd_end);
-        let line = std::str::from_utf8(line_bytes.as_slice())
-            .map_err(|e| Error::Handshake(e.to_string()))?;
-
-        trace!("Reading {line}");
-        line.parse()
-    }
-
-    fn mechanism(&self) -> Result<&AuthMechanism> {
-        self.mechanisms
-            .front()
-            .ok_or_else(|| Error::Handshake("Exhausted available AUTH mechanisms".into()))
-    }
-}
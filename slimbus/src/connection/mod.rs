@@ -1,27 +1,65 @@
 //! Connection API.
 use log::trace;
-use std::os::fd::{AsFd, AsRawFd, RawFd};
+use nix::sys::socket::{getsockopt, setsockopt, sockopt};
+use std::collections::VecDeque;
+use std::io;
+use std::ops::ControlFlow;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
 use std::sync::OnceLock;
-use zbus_names::OwnedUniqueName;
+use std::time::{Duration, Instant};
+use zbus_names::{OwnedUniqueName, WellKnownName};
+use zvariant::DynamicType;
 
-use crate::{address, Address};
-use crate::{message::Message, Error, Result};
+use crate::{
+    address::{self, BusType},
+    Address,
+};
+use crate::{
+    fdo::{self, DBusSignal},
+    message::{self, header::MAX_MESSAGE_SIZE, EndianSig, Flags, Message},
+    object_server,
+    utils::wait_readable,
+    Error, MatchRule, OwnedGuid, Result,
+};
+
+mod builder;
+pub use builder::Builder;
 
 pub mod socket;
 pub use socket::Socket;
 
+mod pending_calls;
+pub use pending_calls::PendingCalls;
+
+mod send_queue;
+use send_queue::SendQueue;
+
 mod socket_reader;
-pub use socket_reader::SocketReader;
+pub use socket_reader::{ReaderStats, SocketReader, WakeupHandle};
 
 pub(crate) mod handshake;
-use handshake::Authenticated;
+pub use handshake::{
+    AuthIdFromEnv, AuthIdFromFile, AuthIdProvider, ClientHandshakeMachine, EffectiveUid,
+    HandshakeProgress,
+};
+use handshake::{AuthMechanism, Authenticated, ClientHandshake, Handshake};
+use socket::BoxedSplit;
+
+/// The default time to wait for the reply to a method call, as used by the reference
+/// implementation.
+pub const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_secs(25);
 
 #[derive(Debug)]
 pub struct Connection {
+    id: u64,
+    address: Option<Address>,
+    bus_type: Option<BusType>,
     cap_unix_fd: bool,
     unique_name: OnceLock<OwnedUniqueName>,
 
     socket_write: Box<dyn socket::WriteHalf>,
+    send_queue: Option<SendQueue>,
+    answer_peer: bool,
     raw_fd: RawFd,
 }
 
@@ -35,21 +73,571 @@ impl Connection {
         let serial = msg.primary_header().serial_num();
 
         trace!("Sending message: {:?}", msg);
-        let write = &mut self.socket_write;
+        let fds: Vec<_> = data.fds().iter().map(|f| f.as_fd()).collect();
+        self.write_all(data, &fds)?;
+        trace!("Sent message with serial: {}", serial);
+
+        Ok(())
+    }
+
+    /// Send an already serialized message to the peer, along with `fds`.
+    ///
+    /// This is the lowest-level send primitive, e.g. for forwarding or replaying captured
+    /// messages. Only minimal checks are done: `bytes` must start with a valid endianness
+    /// signature and not exceed the maximum message size, and `fds` can only be passed if
+    /// [`Connection::can_pass_unix_fd`]. It is up to the caller to ensure that `bytes` hold a
+    /// single valid message, referencing `fds` by their index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection, Message};
+    /// use std::os::{fd::AsRawFd, unix::net::UnixStream};
+    ///
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (mut connection, _) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// let msg = Message::method("/", "Ping").unwrap().build(&"raw").unwrap();
+    /// connection.send_raw(msg.data(), &[]).unwrap();
+    /// assert_eq!(reader.read_socket().unwrap(), msg);
+    ///
+    /// assert!(connection.send_raw(b"not a message", &[]).is_err());
+    /// ```
+    pub fn send_raw(&mut self, bytes: &[u8], fds: &[BorrowedFd<'_>]) -> Result<()> {
+        let endian_sig = bytes
+            .first()
+            .ok_or(Error::Variant(zvariant::Error::OutOfBounds))?;
+        EndianSig::try_from(*endian_sig)?;
+        if bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(Error::ExcessData);
+        }
+        if !fds.is_empty() && !self.cap_unix_fd {
+            return Err(Error::Unsupported);
+        }
+
+        trace!("Sending {} raw bytes", bytes.len());
+        self.write_all(bytes, fds)
+    }
+
+    // Write all of `bytes`, passing `fds` along the first chunk. With a send queue, what can't be
+    // written without blocking is queued instead.
+    fn write_all(&mut self, bytes: &[u8], fds: &[BorrowedFd<'_>]) -> Result<()> {
+        let Some(queue) = &mut self.send_queue else {
+            let mut pos = 0;
+            while pos < bytes.len() {
+                let fds = if pos == 0 { fds } else { &[] };
+                pos += self.socket_write.sendmsg(&bytes[pos..], fds)?;
+            }
+
+            return Ok(());
+        };
+
+        // Keep the messages in order behind the ones already queued.
+        if !queue.is_empty() {
+            queue.push(bytes, fds)?;
+            queue.flush(&mut *self.socket_write)?;
+
+            return Ok(());
+        }
+
         let mut pos = 0;
-        while pos < data.len() {
-            let fds = if pos == 0 {
-                data.fds().iter().map(|f| f.as_fd()).collect()
-            } else {
-                vec![]
-            };
-            pos += write.sendmsg(&data[pos..], &fds)?;
+        while pos < bytes.len() {
+            let fds = if pos == 0 { fds } else { &[] };
+            match self.socket_write.sendmsg(&bytes[pos..], fds) {
+                Ok(n) => pos += n,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return queue.push(&bytes[pos..], fds);
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
-        trace!("Sent message with serial: {}", serial);
 
         Ok(())
     }
 
+    /// Queue the messages that can't be written without blocking, rather than failing.
+    ///
+    /// This is for sockets in nonblocking mode (see [`crate::set_blocking`]), where sending faster
+    /// than the peer reads eventually fills the send buffer of the socket. Without a send queue,
+    /// [`Connection::send`] then fails with an error of kind `WouldBlock`, possibly after writing
+    /// part of the message. With it, the rest of the message is queued, as are all the messages
+    /// sent after it, so [`Connection::send`] doesn't block nor fail.
+    ///
+    /// The application is then responsible for applying backpressure: watch the backlog with
+    /// [`Connection::pending_bytes`] and [`Connection::pending_messages`], and call
+    /// [`Connection::flush`] whenever the socket becomes writable until the queue is empty.
+    ///
+    /// In blocking mode, writing never has to be deferred, so the queue stays empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, set_blocking, Connection, Message};
+    /// use std::os::{fd::AsRawFd, unix::net::UnixStream};
+    ///
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (mut connection, _) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// set_blocking(a_fd, false);
+    /// connection.enable_send_queue();
+    /// let payload = vec![0u8; 64 * 1024];
+    /// let mut sent = 0;
+    /// while connection.pending_messages() < 2 {
+    ///     let msg = Message::signal("/", "org.example.Bulk", "Data").unwrap().build(&payload).unwrap();
+    ///     connection.send(&msg).unwrap();
+    ///     sent += 1;
+    /// }
+    /// assert!(connection.pending_bytes() > 64 * 1024);
+    ///
+    /// // Drain the peer until all the messages are written.
+    /// let mut received = 0;
+    /// while !connection.flush().unwrap() {
+    ///     reader.read_socket().unwrap();
+    ///     received += 1;
+    /// }
+    /// assert_eq!(connection.pending_bytes(), 0);
+    /// while received < sent {
+    ///     reader.read_socket().unwrap();
+    ///     received += 1;
+    /// }
+    /// ```
+    pub fn enable_send_queue(&mut self) {
+        self.send_queue.get_or_insert_with(SendQueue::default);
+    }
+
+    /// The number of bytes in the send queue, waiting to be written to the socket.
+    ///
+    /// This is always `0` without a send queue, see [`Connection::enable_send_queue`].
+    pub fn pending_bytes(&self) -> usize {
+        self.send_queue.as_ref().map_or(0, SendQueue::pending_bytes)
+    }
+
+    /// The number of messages in the send queue, including the one partially written.
+    ///
+    /// This is always `0` without a send queue, see [`Connection::enable_send_queue`].
+    pub fn pending_messages(&self) -> usize {
+        self.send_queue
+            .as_ref()
+            .map_or(0, SendQueue::pending_messages)
+    }
+
+    /// Write the messages of the send queue to the socket, until writing would block.
+    ///
+    /// Returns whether the queue is now empty. Call it again once the socket becomes writable
+    /// otherwise. See [`Connection::enable_send_queue`] for details.
+    pub fn flush(&mut self) -> Result<bool> {
+        match &mut self.send_queue {
+            Some(queue) => queue.flush(&mut *self.socket_write),
+            None => Ok(true),
+        }
+    }
+
+    /// Whether file descriptor passing was negotiated with the peer.
+    ///
+    /// Sending a message carrying FDs over a connection where this returns `false` fails with
+    /// [`Error::Unsupported`].
+    pub fn can_pass_unix_fd(&self) -> bool {
+        self.cap_unix_fd
+    }
+
+    /// Set the size of the send buffer of the socket (`SO_SNDBUF`).
+    ///
+    /// A larger buffer reduces the number of syscalls needed to send large or many messages. This
+    /// is only a hint: the kernel may adjust the size (Linux doubles it, for bookkeeping overhead)
+    /// and clamp it to system limits. Use [`Connection::send_buffer_size`] to get the actual size.
+    pub fn set_send_buffer_size(&self, size: usize) -> Result<()> {
+        setsockopt(&self.borrowed_fd(), sockopt::SndBuf, &size).map_err(Into::into)
+    }
+
+    /// The size of the send buffer of the socket (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> Result<usize> {
+        getsockopt(&self.borrowed_fd(), sockopt::SndBuf).map_err(Into::into)
+    }
+
+    /// Set the size of the receive buffer of the socket (`SO_RCVBUF`).
+    ///
+    /// As with [`Connection::set_send_buffer_size`], this is only a hint that the kernel may
+    /// adjust. Use [`Connection::recv_buffer_size`] to get the actual size.
+    pub fn set_recv_buffer_size(&self, size: usize) -> Result<()> {
+        setsockopt(&self.borrowed_fd(), sockopt::RcvBuf, &size).map_err(Into::into)
+    }
+
+    /// The size of the receive buffer of the socket (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> Result<usize> {
+        getsockopt(&self.borrowed_fd(), sockopt::RcvBuf).map_err(Into::into)
+    }
+
+    fn borrowed_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: The socket is owned by the write half, which lives as long as `self`.
+        unsafe { BorrowedFd::borrow_raw(self.raw_fd) }
+    }
+
+    /// Wait until `name` has an owner on the bus, for at most `timeout`.
+    ///
+    /// This adds a match rule for `NameOwnerChanged` signals of `name` and asks the bus for the
+    /// current owner of `name`, then reads messages from `reader` until either tells that the name
+    /// has an owner. The unique name of the owner is returned. If `timeout` expires first,
+    /// [`fdo::Error::TimedOut`] is returned. A timeout too large to represent, such as
+    /// `Duration::MAX`, waits indefinitely.
+    ///
+    /// **Note**: Other messages received while waiting are discarded.
+    pub fn wait_for_name<'n, N>(
+        &mut self,
+        reader: &mut SocketReader,
+        name: N,
+        timeout: Duration,
+    ) -> Result<OwnedUniqueName>
+    where
+        N: TryInto<WellKnownName<'n>>,
+        N::Error: Into<Error>,
+    {
+        let name = name.try_into().map_err(Into::into)?;
+        let deadline = Instant::now().checked_add(timeout);
+
+        let rule = MatchRule::name_owner_changed(name.clone())?;
+        let add_match = bus_method("AddMatch")?
+            .with_flags(Flags::NoReplyExpected)?
+            .build(&rule)?;
+        self.send(&add_match)?;
+
+        // The rule is removed whatever the outcome, so the owner is found in a closure.
+        let owner = (|| -> Result<OwnedUniqueName> {
+            let get_owner = bus_method("GetNameOwner")?.build(&name)?;
+            self.send(&get_owner)?;
+            let serial = get_owner.primary_header().serial_num();
+
+            loop {
+                let remaining = time_left(deadline);
+                if !reader.has_buffered_data() && !wait_readable(self.raw_fd, remaining)? {
+                    break Err(fdo::Error::TimedOut(format!("`{name}` has no owner")).into());
+                }
+
+                let msg = reader.read_socket()?;
+                if self.answer_peer_call(&msg)? {
+                    continue;
+                }
+                let header = msg.header();
+                if header.reply_serial() == Some(serial) {
+                    match header.message_type() {
+                        message::Type::MethodReturn => break msg.body().deserialize(),
+                        message::Type::Error
+                            if header.error_name().map(|e| e.as_str())
+                                == Some("org.freedesktop.DBus.Error.NameHasNoOwner") =>
+                        {
+                            continue
+                        }
+                        _ => break Err(Error::from(msg.clone())),
+                    }
+                }
+
+                if let Some(DBusSignal::NameOwnerChanged {
+                    name: changed,
+                    new_owner: Some(owner),
+                    ..
+                }) = DBusSignal::from_message(&msg)
+                {
+                    if changed.as_str() == name.as_str() {
+                        break Ok(owner);
+                    }
+                }
+            }
+        })();
+
+        // An error while waiting takes precedence over one removing the rule.
+        let removed = bus_method("RemoveMatch")
+            .and_then(|m| m.with_flags(Flags::NoReplyExpected))
+            .and_then(|m| m.build(&rule))
+            .and_then(|remove_match| self.send(&remove_match));
+        let owner = owner?;
+        removed?;
+
+        Ok(owner)
+    }
+
+    /// Call a method, retrying once with interactive authorization allowed if the service asks
+    /// for it.
+    ///
+    /// The call is built from `call` and `body`, sent, and its reply read from `reader`. If the
+    /// reply is an `org.freedesktop.DBus.Error.InteractiveAuthorizationRequired` error, the call is
+    /// built again with [`Flags::AllowInteractiveAuth`] set and sent once more, so the service may
+    /// prompt the user for authorization (e.g. through polkit). An error reply is returned as
+    /// [`Error::MethodError`].
+    ///
+    /// **Note**: Other messages received while waiting are discarded.
+    pub fn call_with_interactive_auth<B>(
+        &mut self,
+        reader: &mut SocketReader,
+        call: message::Builder<'_>,
+        body: &B,
+    ) -> Result<Message>
+    where
+        B: serde::ser::Serialize + DynamicType,
+    {
+        let mut reply = self.send_and_wait_reply(reader, &call.clone().build(body)?)?;
+        let auth_required = reply.header().error_name().map(|e| e.as_str())
+            == Some("org.freedesktop.DBus.Error.InteractiveAuthorizationRequired");
+        if auth_required {
+            trace!("Interactive authorization required, retrying");
+            let retry = call.with_flags(Flags::AllowInteractiveAuth)?.build(body)?;
+            reply = self.send_and_wait_reply(reader, &retry)?;
+        }
+
+        match reply.message_type() {
+            message::Type::Error => Err(reply.into()),
+            _ => Ok(reply),
+        }
+    }
+
+    /// Send the method call `msg` and read its reply from `reader`.
+    ///
+    /// This is [`Connection::call_method_timeout`] with [`DEFAULT_REPLY_TIMEOUT`], so a call to a
+    /// service that never replies fails with [`fdo::Error::NoReply`] instead of blocking forever.
+    pub fn call_method(&mut self, reader: &mut SocketReader, msg: &Message) -> Result<Message> {
+        self.call_method_timeout(reader, msg, DEFAULT_REPLY_TIMEOUT)
+    }
+
+    /// Send the method call `msg` and read its reply from `reader`, for at most `timeout`.
+    ///
+    /// If no reply is received before `timeout` expires, [`fdo::Error::NoReply`] is returned. An
+    /// error reply is returned as [`Error::MethodError`]. A timeout too large to represent, such as
+    /// `Duration::MAX`, waits indefinitely.
+    ///
+    /// **Note**: Other messages received while waiting are discarded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection, Message};
+    /// use std::{os::{fd::AsRawFd, unix::net::UnixStream}, time::Duration};
+    ///
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (mut conn, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (mut peer, _) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// let call = Message::method("/", "Ping").unwrap().build(&()).unwrap();
+    /// peer.send(&Message::method_reply(&call).unwrap().build(&42u32).unwrap()).unwrap();
+    ///
+    /// let reply = conn.call_method_timeout(&mut reader, &call, Duration::MAX).unwrap();
+    /// assert_eq!(reply.body().deserialize::<u32>().unwrap(), 42);
+    /// ```
+    pub fn call_method_timeout(
+        &mut self,
+        reader: &mut SocketReader,
+        msg: &Message,
+        timeout: Duration,
+    ) -> Result<Message> {
+        let reply = self.send_and_wait_reply_timeout(reader, msg, timeout)?;
+
+        match reply.message_type() {
+            message::Type::Error => Err(reply.into()),
+            _ => Ok(reply),
+        }
+    }
+
+    /// Ask the bus to route the messages matching `rule` to this connection, and wait for it to
+    /// accept the rule.
+    ///
+    /// Messages received while waiting are discarded, so add the rules before expecting the
+    /// messages they match. If the bus rejects `rule` as malformed,
+    /// [`fdo::Error::MatchRuleInvalid`] is returned, rather than the rule silently never matching.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use slimbus::{fdo, Connection, Error, MatchRule};
+    /// # (|| -> slimbus::Result<()> {
+    /// let (mut conn, mut reader, _) = Connection::session_with_hello()?;
+    /// let rule = MatchRule::properties_changed("org.example.Player", "/org/example/player")?;
+    /// conn.add_match(&mut reader, &rule)?;
+    ///
+    /// let res = conn.add_match(&mut reader, &MatchRule::from("type='nonsense'"));
+    /// assert!(matches!(res, Err(Error::FDO(e)) if matches!(*e, fdo::Error::MatchRuleInvalid(_))));
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn add_match(&mut self, reader: &mut SocketReader, rule: &MatchRule) -> Result<()> {
+        let add_match = bus_method("AddMatch")?.build(rule)?;
+        self.call_method(reader, &add_match).map_err(|e| {
+            fdo::typed_error(
+                e,
+                "org.freedesktop.DBus.Error.MatchRuleInvalid",
+                fdo::Error::MatchRuleInvalid,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Ask the bus to stop routing the messages matching `rule`, added with
+    /// [`Connection::add_match`], and wait for it to remove the rule.
+    ///
+    /// Messages received while waiting are discarded. If the connection has no such rule,
+    /// [`fdo::Error::MatchRuleNotFound`] is returned.
+    pub fn remove_match(&mut self, reader: &mut SocketReader, rule: &MatchRule) -> Result<()> {
+        let remove_match = bus_method("RemoveMatch")?.build(rule)?;
+        self.call_method(reader, &remove_match).map_err(|e| {
+            fdo::typed_error(
+                e,
+                "org.freedesktop.DBus.Error.MatchRuleNotFound",
+                fdo::Error::MatchRuleNotFound,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Read messages from `reader` and pass each of them to `handler`, until it breaks.
+    ///
+    /// The handler gets the connection too, e.g. to reply to calls. The loop stops when the handler
+    /// returns [`ControlFlow::Break`] or an error, which is returned. The socket may be in either
+    /// blocking or nonblocking mode: the loop waits for it to become readable before reading.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::ops::ControlFlow;
+    /// # use slimbus::{message::Type, Connection, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let (mut connection, mut reader, _) = Connection::session_with_hello()?;
+    ///
+    /// connection.run(&mut reader, |connection, msg| {
+    ///     if msg.message_type() == Type::MethodCall {
+    ///         connection.send(&Message::method_reply(&msg)?.build(&())?)?;
+    ///     }
+    ///
+    ///     Ok(ControlFlow::Continue(()))
+    /// })
+    /// # })().unwrap()
+    /// ```
+    pub fn run<F>(&mut self, reader: &mut SocketReader, mut handler: F) -> Result<()>
+    where
+        F: FnMut(&mut Self, Message) -> Result<ControlFlow<()>>,
+    {
+        loop {
+            if !reader.has_buffered_data() && !wait_readable(self.raw_fd, Duration::MAX)? {
+                continue;
+            }
+
+            let msg = reader.read_socket()?;
+            if self.answer_peer_call(&msg)? {
+                continue;
+            }
+            if handler(self, msg)?.is_break() {
+                return Ok(());
+            }
+        }
+    }
+
+    // Send the method call `msg` and read messages from `reader` until its reply, for at most
+    // `DEFAULT_REPLY_TIMEOUT`.
+    fn send_and_wait_reply(&mut self, reader: &mut SocketReader, msg: &Message) -> Result<Message> {
+        self.send_and_wait_reply_timeout(reader, msg, DEFAULT_REPLY_TIMEOUT)
+    }
+
+    // Send the method call `msg` and read messages from `reader` until its reply, for at most
+    // `timeout`. Error replies are returned as is.
+    fn send_and_wait_reply_timeout(
+        &mut self,
+        reader: &mut SocketReader,
+        msg: &Message,
+        timeout: Duration,
+    ) -> Result<Message> {
+        let deadline = Instant::now().checked_add(timeout);
+        self.send(msg)?;
+        let mut pending = PendingCalls::new();
+        pending.register(msg.primary_header().serial_num(), ());
+
+        loop {
+            let remaining = time_left(deadline);
+            if !reader.has_buffered_data() && !wait_readable(self.raw_fd, remaining)? {
+                let member = msg.header().member().map(|m| m.to_string());
+                let member = member.as_deref().unwrap_or("method");
+                return Err(fdo::Error::NoReply(format!("`{member}` call timed out")).into());
+            }
+
+            let received = reader.read_socket()?;
+            if let Some(((), reply)) = pending.complete(&received) {
+                return Ok(reply);
+            }
+            self.answer_peer_call(&received)?;
+        }
+    }
+
+    /// Answer the calls to the standard `org.freedesktop.DBus.Peer` interface automatically.
+    ///
+    /// Every peer is expected to implement the `Ping` and `GetMachineId` methods of this interface,
+    /// even a pure client. When enabled, the calls to them received by [`Connection::run`] are
+    /// answered without being passed to the handler, as are the ones received while waiting for a
+    /// reply, e.g. in [`Connection::call_method`], or for a name in [`Connection::wait_for_name`].
+    ///
+    /// This is disabled by default. An [`ObjectServer`](crate::ObjectServer) answers these calls
+    /// on its own.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection, Message};
+    /// use std::{ops::ControlFlow, os::{fd::AsRawFd, unix::net::UnixStream}};
+    ///
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (mut client, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (mut peer, mut peer_reader) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// client.set_answer_peer(true);
+    /// let ping = Message::method("/", "Ping")
+    ///     .unwrap()
+    ///     .interface("org.freedesktop.DBus.Peer")
+    ///     .unwrap()
+    ///     .build(&())
+    ///     .unwrap();
+    /// peer.send(&ping).unwrap();
+    /// let other = Message::signal("/", "org.example.App", "Done").unwrap().build(&()).unwrap();
+    /// peer.send(&other).unwrap();
+    ///
+    /// // Only the signal reaches the handler.
+    /// client
+    ///     .run(&mut reader, |_, msg| {
+    ///         assert_eq!(msg, other);
+    ///         Ok(ControlFlow::Break(()))
+    ///     })
+    ///     .unwrap();
+    /// let reply = peer_reader.read_socket().unwrap();
+    /// assert_eq!(reply.header().reply_serial(), Some(ping.primary_header().serial_num()));
+    /// ```
+    pub fn set_answer_peer(&mut self, enabled: bool) {
+        self.answer_peer = enabled;
+    }
+
+    // Answer `msg` if it is a call to the `Peer` interface and those are answered automatically.
+    // Returns whether it was handled.
+    fn answer_peer_call(&mut self, msg: &Message) -> Result<bool> {
+        if !self.answer_peer
+            || msg.message_type() != message::Type::MethodCall
+            || msg.header().interface().map(|i| i.as_str()) != Some(object_server::PEER)
+        {
+            return Ok(false);
+        }
+
+        if let Some(reply) = object_server::reply_to_peer(msg)? {
+            self.send(&reply)?;
+        }
+
+        Ok(true)
+    }
+
     /// The unique name of the connection, if set/applicable.
     ///
     /// The unique name is assigned by the message bus or set manually using
@@ -58,27 +646,128 @@ impl Connection {
         self.unique_name.get()
     }
 
-    pub(crate) fn new(auth: Authenticated, raw_fd: RawFd) -> Result<Self> {
-        let cap_unix_fd = auth.cap_unix_fd;
+    /// The process-unique id of the connection.
+    ///
+    /// The id is shared with the [`SocketReader`] the connection was created with, so
+    /// [`SocketReader::connection_id`] can be used to check that a reader belongs to this
+    /// connection.
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection};
+    /// use std::os::{fd::AsRawFd, unix::net::UnixStream};
+    ///
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (connection, reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (other, _) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// assert_eq!(connection.id(), reader.connection_id());
+    /// assert_ne!(other.id(), reader.connection_id());
+    /// ```
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The address the connection was established to.
+    ///
+    /// This is only known for connections built from an [`Address`], e.g. with
+    /// [`Connection::session`] or [`build`], and `None` for ones built from a stream or socket.
+    pub fn address(&self) -> Option<&Address> {
+        self.address.as_ref()
+    }
 
+    /// The type of bus the connection is to.
+    ///
+    /// This is determined once, when the connection is built: connections built with
+    /// [`Builder::session`] or [`Builder::system`] are to that bus, while for other addresses it's
+    /// inferred with [`Address::bus_type`]. It's `None` for peer-to-peer connections, including
+    /// the ones built from a stream or socket.
+    pub fn bus_type(&self) -> Option<BusType> {
+        self.bus_type
+    }
+
+    /// Create a `Connection` from the halves of an already authenticated socket.
+    ///
+    /// No handshake is performed, this is for sockets authenticated elsewhere, e.g. by a
+    /// [`ClientHandshakeMachine`] or by the process that handed the socket over.
+    /// `already_received_bytes` are the bytes received past the end of the handshake,
+    /// `cap_unix_fd` whether file descriptor passing was negotiated and `raw_fd` the file
+    /// descriptor of the socket, used to wait for it to become readable.
+    pub fn from_halves(
+        socket_write: Box<dyn socket::WriteHalf>,
+        socket_read: Box<dyn socket::ReadHalf>,
+        already_received_bytes: Vec<u8>,
+        cap_unix_fd: bool,
+        raw_fd: RawFd,
+    ) -> (Self, SocketReader) {
+        let mut reader = SocketReader::new(socket_read, already_received_bytes);
+        reader.set_raw_fd(raw_fd);
         let connection = Self {
-            socket_write: auth.socket_write,
+            id: reader.connection_id(),
+            address: None,
+            bus_type: None,
+            socket_write,
+            send_queue: None,
+            answer_peer: false,
             cap_unix_fd,
             unique_name: OnceLock::new(),
             raw_fd,
         };
 
-        Ok(connection)
+        (connection, reader)
     }
 
     /// Create a `Connection` to the session/user message bus.
+    ///
+    /// All the addresses listed for the bus are tried in turn, see [`Builder::session`].
     pub fn session() -> Result<(Self, SocketReader)> {
-        build(Address::session()?)
+        Builder::session()?.build()
     }
 
     /// Create a `Connection` to the system-wide message bus.
+    ///
+    /// All the addresses listed for the bus are tried in turn, see [`Builder::system`].
     pub fn system() -> Result<(Self, SocketReader)> {
-        build(Address::system()?)
+        Builder::system()?.build()
+    }
+
+    /// Create a `Connection` to the session/user message bus and register it on the bus.
+    ///
+    /// See [`Connection::hello`] for details.
+    pub fn session_with_hello() -> Result<(Self, SocketReader, OwnedUniqueName)> {
+        let (mut connection, mut reader) = Self::session()?;
+        let unique_name = connection.hello(&mut reader)?;
+
+        Ok((connection, reader, unique_name))
+    }
+
+    /// Create a `Connection` to the system-wide message bus and register it on the bus.
+    ///
+    /// See [`Connection::hello`] for details.
+    pub fn system_with_hello() -> Result<(Self, SocketReader, OwnedUniqueName)> {
+        let (mut connection, mut reader) = Self::system()?;
+        let unique_name = connection.hello(&mut reader)?;
+
+        Ok((connection, reader, unique_name))
+    }
+
+    /// Register the connection on the message bus by calling its `Hello` method.
+    ///
+    /// This must be the first call on a bus connection. It waits for the reply, sets the unique
+    /// name of the connection that the bus assigned and returns it.
+    ///
+    /// **Note**: Other messages received while waiting are discarded.
+    pub fn hello(&mut self, reader: &mut SocketReader) -> Result<OwnedUniqueName> {
+        let reply = self.send_and_wait_reply(reader, &bus_method("Hello")?.build(&())?)?;
+        if reply.message_type() == message::Type::Error {
+            return Err(reply.into());
+        }
+        let unique_name: OwnedUniqueName = reply.body().deserialize()?;
+        let _ = self.unique_name.set(unique_name.clone());
+
+        Ok(unique_name)
     }
 }
 
@@ -88,28 +777,96 @@ impl AsRawFd for Connection {
     }
 }
 
-/// Build the connection, consuming the builder.
-///
-/// # Errors
+// The time left until `deadline`, `None` meaning there's no deadline at all.
+fn time_left(deadline: Option<Instant>) -> Duration {
+    deadline.map_or(Duration::MAX, |d| {
+        d.saturating_duration_since(Instant::now())
+    })
+}
+
+// Create a builder for a call to a method of the bus itself.
+fn bus_method(method: &'static str) -> Result<message::Builder<'static>> {
+    Message::method("/org/freedesktop/DBus", method)?
+        .destination("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")
+}
+
+/// Build the connection to `address`.
 ///
-/// Until server-side bus connection is supported, attempting to build such a connection will
-/// result in [`Error::Unsupported`] error.
+/// This is a shorthand for [`Builder::address`] without further options.
 pub fn build(address: Address) -> Result<(Connection, SocketReader)> {
-    let server_guid = address.guid().map(|g| g.to_owned().into());
-    let (raw_fd, stream) = match address.connect()? {
-        address::transport::Stream::Unix(stream) => (stream.as_raw_fd(), stream.into()),
-        address::transport::Stream::Tcp(stream) => (stream.as_raw_fd(), stream.into()),
+    Builder::address(address).build()
+}
+
+/// Build the connection over an already connected `stream`.
+///
+/// This is useful together with [`Address::connect`] or [`Address::connect_nonblocking`]. The
+/// stream must be in blocking mode and its connection established.
+///
+/// File descriptor passing is only negotiated if `unix_fd` is `true` and the transport supports
+/// it. Passing `false` gives a connection that behaves as if the transport could not pass file
+/// descriptors: [`Connection::send`] rejects messages carrying any, and the peer may not send any
+/// either.
+///
+/// # Example
+///
+/// ```no_run
+/// # use slimbus::{connection, Address};
+/// # (|| -> slimbus::Result<()> {
+/// let stream = Address::session()?.connect()?;
+/// let (conn, _reader) = connection::build_from_stream(stream, None, false)?;
+///
+/// assert!(!conn.can_pass_unix_fd());
+/// # Ok(()) })().unwrap()
+/// ```
+pub fn build_from_stream(
+    stream: address::Stream,
+    server_guid: Option<OwnedGuid>,
+    unix_fd: bool,
+) -> Result<(Connection, SocketReader)> {
+    build_stream(stream, |socket| {
+        Authenticated::client(socket, server_guid, None, unix_fd, None)
+    })
+}
+
+// Build the connection over `stream`, authenticating as `auth_id` with `mechanisms` if given.
+fn build_authenticated(
+    stream: address::Stream,
+    server_guid: Option<OwnedGuid>,
+    auth_id: Option<Box<dyn AuthIdProvider>>,
+    mechanisms: Option<VecDeque<AuthMechanism>>,
+) -> Result<(Connection, SocketReader)> {
+    build_stream(stream, |socket| {
+        let mut handshake = ClientHandshake::new(socket, mechanisms, server_guid);
+        if let Some(auth_id) = auth_id {
+            handshake = handshake.set_auth_id_provider(auth_id);
+        }
+
+        handshake.perform()
+    })
+}
+
+// Build the connection over `stream`, once `handshake` authenticated on it.
+fn build_stream(
+    stream: address::Stream,
+    handshake: impl FnOnce(BoxedSplit) -> Result<Authenticated>,
+) -> Result<(Connection, SocketReader)> {
+    let (raw_fd, stream) = match stream {
+        address::Stream::Unix(stream) => (stream.as_raw_fd(), stream.into()),
+        address::Stream::Tcp(stream) => (stream.as_raw_fd(), stream.into()),
     };
 
-    let mut auth = Authenticated::client(stream, server_guid, None)?;
+    let mut auth = handshake(stream)?;
 
     // SAFETY: `Authenticated` is always built with these fields set to `Some`.
     let socket_read = auth.socket_read.take().unwrap();
     let already_received_bytes = auth.already_received_bytes.take().unwrap();
 
-    let conn = Connection::new(auth, raw_fd)?;
-
-    let reader = SocketReader::new(socket_read, already_received_bytes);
-
-    Ok((conn, reader))
+    Ok(Connection::from_halves(
+        auth.socket_write,
+        socket_read,
+        already_received_bytes,
+        auth.cap_unix_fd,
+        raw_fd,
+    ))
 }
@@ -0,0 +1,91 @@
+use std::{collections::HashMap, num::NonZeroU32};
+
+use crate::message::{Message, Type};
+
+/// A table of method calls awaiting their reply.
+///
+/// Register each outgoing call under its serial number, together with a slot of arbitrary data
+/// (e.g. a callback, or what to do with the reply). Passing every received message to
+/// [`PendingCalls::complete`] then hands back the slot of the call it replies to. This allows
+/// having many calls in flight at once, with replies arriving in any order.
+///
+/// [`crate::Connection::call_method`] matches the reply to its single call this way.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::{connection::PendingCalls, Message};
+/// # (|| -> slimbus::Result<()> {
+/// let mut pending = PendingCalls::new();
+/// let mut calls = vec![];
+/// let mut completed = vec![];
+/// for name in ["first", "second", "third"] {
+///     let call = Message::method("/", "Ping")?.sender(":1.1")?.build(&())?;
+///     pending.register(call.primary_header().serial_num(), name);
+///     calls.push(call);
+/// }
+///
+/// for call in calls.iter().rev() {
+///     let reply = Message::method_reply(call)?.build(&())?;
+///     let (name, reply) = pending.complete(&reply).unwrap();
+///     assert_eq!(reply.header().reply_serial(), Some(call.primary_header().serial_num()));
+///     completed.push(name);
+/// }
+///
+/// assert_eq!(completed, ["third", "second", "first"]);
+/// assert!(pending.is_empty());
+/// # Ok(()) })().unwrap()
+/// ```
+#[derive(Debug)]
+pub struct PendingCalls<T> {
+    calls: HashMap<NonZeroU32, T>,
+}
+
+impl<T> PendingCalls<T> {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self {
+            calls: HashMap::new(),
+        }
+    }
+
+    /// Register a call with the given `serial`, returning the slot previously registered under
+    /// the same serial, if any.
+    pub fn register(&mut self, serial: NonZeroU32, slot: T) -> Option<T> {
+        self.calls.insert(serial, slot)
+    }
+
+    /// Complete the call that `msg` replies to.
+    ///
+    /// Returns `None` if `msg` is not a method return or error, or doesn't reply to any of the
+    /// registered calls.
+    pub fn complete(&mut self, msg: &Message) -> Option<(T, Message)> {
+        if !matches!(msg.message_type(), Type::MethodReturn | Type::Error) {
+            return None;
+        }
+        let serial = msg.inner.quick_fields.reply_serial()?;
+
+        self.calls.remove(&serial).map(|slot| (slot, msg.clone()))
+    }
+
+    /// Forget about the call with the given `serial`, returning its slot.
+    pub fn cancel(&mut self, serial: NonZeroU32) -> Option<T> {
+        self.calls.remove(&serial)
+    }
+
+    /// The number of calls awaiting a reply.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Whether no call is awaiting a reply.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+}
+
+impl<T> Default for PendingCalls<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    collections::VecDeque,
+    io,
+    os::fd::{AsFd, BorrowedFd, OwnedFd},
+};
+
+use super::socket::WriteHalf;
+use crate::Result;
+
+// The messages that couldn't be written to the socket yet, see `Connection::enable_send_queue`.
+#[derive(Debug, Default)]
+pub(super) struct SendQueue {
+    messages: VecDeque<Outgoing>,
+    pending_bytes: usize,
+}
+
+// A message waiting to be written, from `written` on. The FDs are sent along the first byte.
+#[derive(Debug)]
+struct Outgoing {
+    bytes: Vec<u8>,
+    fds: Vec<OwnedFd>,
+    written: usize,
+}
+
+impl SendQueue {
+    pub(super) fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub(super) fn pending_bytes(&self) -> usize {
+        self.pending_bytes
+    }
+
+    pub(super) fn pending_messages(&self) -> usize {
+        self.messages.len()
+    }
+
+    // Queue a copy of `bytes` and `fds`, the rest of a message that couldn't be written.
+    pub(super) fn push(&mut self, bytes: &[u8], fds: &[BorrowedFd<'_>]) -> Result<()> {
+        let fds = fds
+            .iter()
+            .map(|fd| fd.try_clone_to_owned())
+            .collect::<io::Result<_>>()?;
+        self.messages.push_back(Outgoing {
+            bytes: bytes.to_vec(),
+            fds,
+            written: 0,
+        });
+        self.pending_bytes += bytes.len();
+
+        Ok(())
+    }
+
+    // Write the queued messages to `socket`, until it would block. Returns whether the queue got
+    // empty.
+    pub(super) fn flush(&mut self, socket: &mut dyn WriteHalf) -> Result<bool> {
+        while let Some(msg) = self.messages.front_mut() {
+            while msg.written < msg.bytes.len() {
+                let fds: Vec<_> = match msg.written {
+                    0 => msg.fds.iter().map(|fd| fd.as_fd()).collect(),
+                    _ => vec![],
+                };
+                match socket.sendmsg(&msg.bytes[msg.written..], &fds) {
+                    Ok(n) => {
+                        msg.written += n;
+                        self.pending_bytes -= n;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            self.messages.pop_front();
+        }
+
+        Ok(true)
+    }
+}
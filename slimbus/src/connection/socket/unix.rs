@@ -67,7 +67,27 @@ fn fd_recvmsg(fd: RawFd, buffer: &mut [u8]) -> io::Result<(usize, Vec<OwnedFd>)>
     let mut iov = [IoSliceMut::new(buffer)];
     let mut cmsgspace = cmsg_space!([RawFd; FDS_MAX]);
 
-    let msg = recvmsg::<UnixAddr>(fd, &mut iov, Some(&mut cmsgspace), MsgFlags::empty())?;
+    // Don't leak the received FDs into child processes.
+    #[cfg(any(
+        target_os = "android",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "linux",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    let flags = MsgFlags::MSG_CMSG_CLOEXEC;
+    #[cfg(not(any(
+        target_os = "android",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "linux",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )))]
+    let flags = MsgFlags::empty();
+
+    let msg = recvmsg::<UnixAddr>(fd, &mut iov, Some(&mut cmsgspace), flags)?;
     if msg.bytes == 0 {
         return Err(io::Error::new(
             io::ErrorKind::BrokenPipe,
@@ -1,20 +1,111 @@
+use std::{
+    io::{self, Read, Write},
+    os::{
+        fd::{AsRawFd, OwnedFd, RawFd},
+        unix::net::UnixStream,
+    },
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
 use zvariant::{
     serialized::{self, Context},
     Endian,
 };
 
 use crate::{
-    message::header::{PrimaryHeader, MAX_MESSAGE_SIZE, MIN_MESSAGE_SIZE},
-    padding_for_8_bytes, Message,
+    message::{
+        header::{declared_message_len, PrimaryHeader, MAX_MESSAGE_SIZE, MIN_MESSAGE_SIZE},
+        MessageRef, Sequence,
+    },
+    utils::{wait_any_readable, wait_readable},
+    Message,
 };
 
 use super::socket::ReadHalf;
 
+/// Counters of the work done by a [`SocketReader`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ReaderStats {
+    recvmsg_calls: u64,
+    bytes_read: u64,
+    messages: u64,
+    fds_received: u64,
+}
+
+impl ReaderStats {
+    /// The number of `recvmsg` calls made on the socket.
+    pub fn recvmsg_calls(&self) -> u64 {
+        self.recvmsg_calls
+    }
+
+    /// The number of bytes read from the socket.
+    ///
+    /// This doesn't include bytes already received during the handshake.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// The number of messages produced.
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// The number of file descriptors received.
+    pub fn fds_received(&self) -> u64 {
+        self.fds_received
+    }
+}
+
+/// A handle to cancel blocking reads of a [`SocketReader`], from any thread.
+///
+/// Created with [`SocketReader::wakeup_handle`].
+#[derive(Clone, Debug)]
+pub struct WakeupHandle {
+    socket: Arc<UnixStream>,
+}
+
+impl WakeupHandle {
+    /// Make the current or next read of the [`SocketReader`] return [`crate::Error::Cancelled`].
+    ///
+    /// Waking up several times before a read is cancelled only cancels that one read.
+    pub fn wake(&self) -> crate::Result<()> {
+        match (&*self.socket).write(&[0]) {
+            // The socket buffer being full means a wakeup is already pending.
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// Get a new process-unique connection id. Zero is reserved for messages not received from a
+// connection.
+fn next_conn_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
 #[derive(Debug)]
 pub struct SocketReader {
     socket: Box<dyn ReadHalf>,
     already_received_bytes: Option<Vec<u8>>,
+    conn_id: u64,
     prev_seq: u64,
+    stats: ReaderStats,
+    record_recv_time: bool,
+    cache_headers: bool,
+    raw_fd: Option<RawFd>,
+    // An error that occurred while reading a batch, to return on the next read.
+    pending_error: Option<crate::Error>,
+    // The receiving end of the wakeup handles, and a sending end to create more handles from.
+    wakeup: Option<(UnixStream, WakeupHandle)>,
+    // The buffer reused by `read_borrowed`.
+    borrowed_buf: Vec<u8>,
 }
 
 impl SocketReader {
@@ -22,15 +113,371 @@ impl SocketReader {
         Self {
             socket,
             already_received_bytes: Some(already_received_bytes),
+            conn_id: next_conn_id(),
             prev_seq: 0,
+            stats: ReaderStats::default(),
+            record_recv_time: false,
+            cache_headers: false,
+            raw_fd: None,
+            pending_error: None,
+            wakeup: None,
+            borrowed_buf: Vec::new(),
         }
     }
 
+    // Set the file descriptor of the socket, used to know whether more data is available.
+    pub(crate) fn set_raw_fd(&mut self, raw_fd: RawFd) {
+        self.raw_fd = Some(raw_fd);
+    }
+
+    /// Whether to record when each message is received, available from [`Message::recv_time`].
+    ///
+    /// This is disabled by default, to avoid querying the clock for every message.
+    pub fn set_record_recv_time(&mut self, record: bool) {
+        self.record_recv_time = record;
+    }
+
+    /// Whether to reassemble the header of each message as it's received, rather than on every
+    /// call to [`Message::header`].
+    ///
+    /// The header is then cached in the message, so that its consumer doesn't pay for it, and
+    /// pays less when calling [`Message::header`] repeatedly. This is disabled by default, since
+    /// not every consumer inspects the header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::os::{fd::AsRawFd, unix::net::UnixStream};
+    /// # use slimbus::{connection::socket::BoxedSplit, Connection, Message};
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_conn, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (mut sender, _) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// reader.set_cache_headers(true);
+    /// let signal = Message::signal("/org/example", "org.example.Clock", "Tick").unwrap();
+    /// sender.send(&signal.build(&()).unwrap()).unwrap();
+    ///
+    /// let msg = reader.read_socket().unwrap();
+    /// assert_eq!(msg.header().member().unwrap(), "Tick");
+    /// assert_eq!(msg.header().path().unwrap(), "/org/example");
+    /// ```
+    pub fn set_cache_headers(&mut self, cache: bool) {
+        self.cache_headers = cache;
+    }
+
+    /// The process-unique id of the connection this reader belongs to.
+    ///
+    /// Readers created along with a [`Connection`] share its id, see [`Connection::id`]. The id
+    /// is also used to tell apart the [`Sequence`] of messages received by different readers.
+    ///
+    /// [`Connection`]: crate::Connection
+    /// [`Connection::id`]: crate::Connection::id
+    pub fn connection_id(&self) -> u64 {
+        self.conn_id
+    }
+
+    /// Get a handle to cancel blocking reads from another thread, e.g. to shut down a dedicated
+    /// reader thread.
+    ///
+    /// Once a handle was created, reads first wait for either the socket or a wakeup, and a read
+    /// cancelled with [`WakeupHandle::wake`] returns [`crate::Error::Cancelled`]. This requires
+    /// the socket file descriptor to be known, i.e. this reader to be created along a
+    /// [`crate::Connection`]; otherwise a pending wakeup is only noticed before starting to read.
+    /// A read blocked in the middle of a partially received message can't be cancelled.
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection, Error};
+    /// use std::os::{fd::AsRawFd, unix::net::UnixStream};
+    ///
+    /// let (a, _b) = UnixStream::pair().unwrap();
+    /// let fd = a.as_raw_fd();
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_connection, mut reader) = Connection::from_halves(write, read, vec![], false, fd);
+    ///
+    /// let handle = reader.wakeup_handle().unwrap();
+    /// let thread = std::thread::spawn(move || reader.read_socket());
+    /// handle.wake().unwrap();
+    ///
+    /// assert_eq!(thread.join().unwrap().unwrap_err(), Error::Cancelled);
+    /// ```
+    pub fn wakeup_handle(&mut self) -> crate::Result<WakeupHandle> {
+        if let Some((_, handle)) = &self.wakeup {
+            return Ok(handle.clone());
+        }
+
+        let (receiver, sender) = UnixStream::pair()?;
+        receiver.set_nonblocking(true)?;
+        sender.set_nonblocking(true)?;
+        let handle = WakeupHandle {
+            socket: Arc::new(sender),
+        };
+        self.wakeup = Some((receiver, handle.clone()));
+
+        Ok(handle)
+    }
+
+    // Wait for the socket to become readable or a wakeup, and consume the wakeup, if any.
+    //
+    // Returns `true` if the read is cancelled.
+    fn wait_wakeup(&mut self) -> io::Result<bool> {
+        let Some((receiver, _)) = &self.wakeup else {
+            return Ok(false);
+        };
+        let receiver_fd = receiver.as_raw_fd();
+        let woken = match self.raw_fd {
+            // Only wait on sockets in blocking mode, nonblocking reads shouldn't block.
+            Some(fd) if !self.has_buffered_data() && !is_nonblocking(fd) => {
+                wait_any_readable(&[fd, receiver_fd], Duration::MAX)? == Some(1)
+            }
+            _ => wait_readable(receiver_fd, Duration::ZERO)?,
+        };
+        if woken {
+            // Drain all the wakeups, they are for this read.
+            let mut buf = [0; 64];
+            let mut receiver = receiver;
+            while matches!(receiver.read(&mut buf), Ok(n) if n > 0) {}
+        }
+
+        Ok(woken)
+    }
+
+    /// Counters of the work done by this reader so far.
+    pub fn stats(&self) -> ReaderStats {
+        self.stats
+    }
+
+    // Whether bytes of the next message were already received, e.g. during the handshake.
+    pub(crate) fn has_buffered_data(&self) -> bool {
+        self.already_received_bytes
+            .as_ref()
+            .is_some_and(|b| !b.is_empty())
+    }
+
+    /// Read up to `max` messages.
+    ///
+    /// The first message is read as with [`SocketReader::read_socket`], which blocks if the socket
+    /// is in blocking mode. Further messages are only read as long as more data is available on
+    /// the socket without waiting, so an event loop can process messages in bounded chunks instead
+    /// of buffering everything received. Without a known socket file descriptor, i.e. if this
+    /// reader wasn't created along a [`crate::Connection`], only data already received is read
+    /// after the first message.
+    ///
+    /// An empty `Vec` is returned if `max` is `0`. If an error occurs after the first message, the
+    /// messages read so far are returned and the error is returned by the next read.
+    pub fn read_batch(&mut self, max: usize) -> crate::Result<Vec<Message>> {
+        let mut messages = Vec::new();
+        while messages.len() < max {
+            if !messages.is_empty() && !self.has_available_data() {
+                break;
+            }
+
+            match self.read_socket() {
+                Ok(msg) => messages.push(msg),
+                Err(e) if messages.is_empty() => return Err(e),
+                Err(e) => {
+                    self.pending_error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Read messages and pass them to `handler` until none arrives for `idle`.
+    ///
+    /// This is meant for a graceful shutdown, to process the messages already sent by the peer,
+    /// e.g. final signals, before disconnecting. The number of messages read is returned. Without
+    /// a known socket file descriptor, i.e. if this reader wasn't created along a
+    /// [`crate::Connection`], only data already received is read. The peer closing the connection
+    /// between two messages ends the draining as well, while an error returned by `handler` stops
+    /// it and is returned.
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection, Message};
+    /// use std::{os::{fd::AsRawFd, unix::net::UnixStream}, time::Duration};
+    ///
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (mut sender, _) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// let signal = Message::signal("/", "org.example.Interface", "Final").unwrap();
+    /// for _ in 0..3 {
+    ///     sender.send(&signal.clone().build(&()).unwrap()).unwrap();
+    /// }
+    /// drop(sender);
+    ///
+    /// let mut members = vec![];
+    /// let drained = reader
+    ///     .drain(Duration::from_secs(60), |msg| {
+    ///         members.push(msg.header().member().unwrap().to_string());
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(drained, 3);
+    /// assert_eq!(members, ["Final"; 3]);
+    /// ```
+    pub fn drain<F>(&mut self, idle: Duration, mut handler: F) -> crate::Result<usize>
+    where
+        F: FnMut(Message) -> crate::Result<()>,
+    {
+        let mut count = 0;
+        loop {
+            if self.pending_error.is_none() && !self.has_buffered_data() {
+                let readable = match self.raw_fd {
+                    Some(fd) => wait_readable(fd, idle)?,
+                    None => false,
+                };
+                if !readable {
+                    return Ok(count);
+                }
+            }
+
+            let msg = match self.read_socket() {
+                Err(crate::Error::InputOutput(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(count);
+                }
+                res => res?,
+            };
+            handler(msg)?;
+            count += 1;
+        }
+    }
+
+    // Whether more data can be read without blocking.
+    fn has_available_data(&self) -> bool {
+        self.has_buffered_data()
+            || self
+                .raw_fd
+                .is_some_and(|fd| wait_readable(fd, Duration::ZERO).unwrap_or(false))
+    }
+
+    /// Read the next message, blocking until it is fully received.
+    ///
+    /// If the peer closes the connection in the middle of a message,
+    /// [`crate::Error::TruncatedMessage`] reports how much of it was received. A message declaring
+    /// a length over the maximum message size results in [`crate::Error::ExcessData`], without
+    /// reading the rest of it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, zvariant::Endian, Connection, Error, Message};
+    /// use std::{io::Write, os::{fd::AsRawFd, unix::net::UnixStream}};
+    ///
+    /// let (a, mut b) = UnixStream::pair().unwrap();
+    /// let a_fd = a.as_raw_fd();
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    ///
+    /// // A body length of 4 GiB.
+    /// let msg = Message::method("/", "Ping").unwrap().endian(Endian::Little).build(&()).unwrap();
+    /// let mut oversized = msg.data().to_vec();
+    /// oversized[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    /// b.write_all(&oversized).unwrap();
+    /// assert_eq!(reader.read_socket().unwrap_err(), Error::ExcessData);
+    /// ```
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection, Error, Message};
+    /// use std::{io::Write, os::{fd::AsRawFd, unix::net::UnixStream}};
+    ///
+    /// let (a, mut b) = UnixStream::pair().unwrap();
+    /// let a_fd = a.as_raw_fd();
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    ///
+    /// let msg = Message::method("/", "Ping").unwrap().build(&"cut short").unwrap();
+    /// b.write_all(&msg.data()[..40]).unwrap();
+    /// drop(b);
+    ///
+    /// let err = reader.read_socket().unwrap_err();
+    /// let expected = Some(msg.data().len());
+    /// assert_eq!(err, Error::TruncatedMessage { read: 40, expected });
+    /// ```
     pub fn read_socket(&mut self) -> crate::Result<Message> {
-        let mut bytes = self
-            .already_received_bytes
-            .take()
-            .unwrap_or_else(|| Vec::with_capacity(MIN_MESSAGE_SIZE));
+        let mut bytes = Vec::new();
+        let (primary_header, fds) = self.read_message(&mut bytes)?;
+
+        let recv_time = self.record_recv_time.then(Instant::now);
+        let seq = self.next_seq();
+        let endian = Endian::from(primary_header.endian_sig());
+        let ctxt = Context::new_dbus(endian, 0);
+        let bytes = serialized::Data::new_fds(bytes, ctxt, fds);
+        let msg = Message::from_raw_parts(bytes, seq, recv_time)?;
+        if self.cache_headers {
+            msg.cache_header();
+        }
+
+        Ok(msg)
+    }
+
+    /// Read the next message into a buffer owned by the reader, and return it borrowing the buffer.
+    ///
+    /// Unlike [`SocketReader::read_socket`], this reuses the same buffer for every message rather
+    /// than allocating one per message, so the message is only valid until the next read. This
+    /// suits handlers that fully process each message before reading the next one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::os::{fd::AsRawFd, unix::net::UnixStream};
+    /// # use slimbus::{connection::socket::BoxedSplit, Connection, Message};
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_conn, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (mut sender, _) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// for volume in [3u32, 7] {
+    ///     let signal = Message::signal("/", "org.example.Mixer", "VolumeChanged").unwrap();
+    ///     sender.send(&signal.build(&volume).unwrap()).unwrap();
+    /// }
+    ///
+    /// let mut total = 0;
+    /// for _ in 0..2 {
+    ///     let msg = reader.read_borrowed().unwrap();
+    ///     assert_eq!(msg.header().member().unwrap(), "VolumeChanged");
+    ///     total += msg.deserialize_body::<u32>().unwrap();
+    /// }
+    /// assert_eq!(total, 10);
+    /// ```
+    pub fn read_borrowed(&mut self) -> crate::Result<MessageRef<'_>> {
+        let mut bytes = std::mem::take(&mut self.borrowed_buf);
+        let res = self.read_message(&mut bytes);
+        self.borrowed_buf = bytes;
+        let (primary_header, fds) = res?;
+
+        let seq = self.next_seq();
+        let endian = Endian::from(primary_header.endian_sig());
+        let ctxt = Context::new_dbus(endian, 0);
+        let bytes = serialized::Data::new_fds(&self.borrowed_buf[..], ctxt, fds);
+        MessageRef::new(bytes, seq)
+    }
+
+    // Receive the next complete message into `bytes`, replacing its contents.
+    fn read_message(
+        &mut self,
+        bytes: &mut Vec<u8>,
+    ) -> crate::Result<(PrimaryHeader, Vec<OwnedFd>)> {
+        if let Some(e) = self.pending_error.take() {
+            return Err(e);
+        }
+        if self.wait_wakeup()? {
+            return Err(crate::Error::Cancelled);
+        }
+
+        bytes.clear();
+        if let Some(already_received_bytes) = self.already_received_bytes.take() {
+            *bytes = already_received_bytes;
+        }
         let mut pos = bytes.len();
         let mut fds = vec![];
         if pos < MIN_MESSAGE_SIZE {
@@ -38,50 +485,187 @@ impl SocketReader {
             // We don't have enough data to make a proper message header yet.
             // Some partial read may be in raw_in_buffer, so we try to complete it
             // until we have MIN_MESSAGE_SIZE bytes
-            //
-            // Given that MIN_MESSAGE_SIZE is 16, this codepath is actually extremely unlikely
-            // to be taken more than once
-            while pos < MIN_MESSAGE_SIZE {
-                let res = self.socket.recvmsg(&mut bytes[pos..])?;
-                let len = {
-                    fds.extend(res.1);
-                    res.0
-                };
-                pos += len;
-                if len == 0 {
-                    return Err(crate::Error::InputOutput(
-                        std::io::Error::new(
-                            std::io::ErrorKind::UnexpectedEof,
-                            "failed to receive message",
-                        )
-                        .into(),
-                    ));
-                }
-            }
+            self.recv_exact(&mut bytes[pos..], &mut fds, pos, None)?;
+            pos = MIN_MESSAGE_SIZE;
         }
 
-        let (primary_header, fields_len) = PrimaryHeader::read(&bytes)?;
-        let header_len = MIN_MESSAGE_SIZE + fields_len as usize;
-        let body_padding = padding_for_8_bytes(header_len);
-        let body_len = primary_header.body_len() as usize;
-        let total_len = header_len + body_padding + body_len;
+        let (primary_header, fields_len) = PrimaryHeader::read(bytes)?;
+        let (_, total_len) = declared_message_len(fields_len, primary_header.body_len())?;
         if total_len > MAX_MESSAGE_SIZE {
             return Err(crate::Error::ExcessData);
         }
 
         // By this point we have a full primary header, so we know the exact length of the complete
-        // message.
+        // message. Already received bytes past it belong to the next messages.
+        if bytes.len() > total_len {
+            self.already_received_bytes = Some(bytes.split_off(total_len));
+        }
         bytes.resize(total_len, 0);
 
         // Now we have an incomplete message; read the rest
-        while pos < total_len {
-            let res = self.socket.recvmsg(&mut bytes[pos..])?;
-            let read = {
-                fds.extend(res.1);
-                res.0
+        if pos < total_len {
+            self.recv_exact(&mut bytes[pos..], &mut fds, pos, Some(total_len))?;
+        }
+        self.stats.messages += 1;
+
+        Ok((primary_header, fds))
+    }
+
+    // The receive position of the next message.
+    fn next_seq(&mut self) -> Sequence {
+        self.prev_seq += 1;
+        Sequence::new(self.conn_id, self.prev_seq)
+    }
+
+    /// Read the next message into `buf`, without allocating a buffer for it.
+    ///
+    /// This is for memory constrained environments: the message is received into the
+    /// caller-provided buffer, which is never grown. On success, the length of the message, which
+    /// is at the start of `buf`, and the file descriptors received with it are returned. Use
+    /// [`Message::from_bytes`] to parse it, which copies it.
+    ///
+    /// If the message is larger than `buf`, [`crate::Error::ExcessData`] is returned. The rest of
+    /// the message is then received into `buf` and discarded, so reading can go on with the next
+    /// message. A message declared larger than the maximum message size is not skipped, as the
+    /// stream can't be trusted anymore: [`crate::Error::ExcessData`] is returned right away, like
+    /// [`SocketReader::read_socket`] does.
+    ///
+    /// ```
+    /// use slimbus::{connection::socket::BoxedSplit, Connection, Error, Message};
+    /// use std::os::{fd::AsRawFd, unix::net::UnixStream};
+    ///
+    /// let (a, b) = UnixStream::pair().unwrap();
+    /// let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, vec![], false, a_fd);
+    /// let (read, write) = BoxedSplit::from(b).take();
+    /// let (mut sender, _) = Connection::from_halves(write, read, vec![], false, b_fd);
+    ///
+    /// let large = Message::method("/", "Large").unwrap().build(&"x".repeat(1024)).unwrap();
+    /// let small = Message::method("/", "Small").unwrap().build(&()).unwrap();
+    /// sender.send(&large).unwrap();
+    /// sender.send(&small).unwrap();
+    ///
+    /// let mut buf = [0; 256];
+    /// assert_eq!(reader.read_socket_into(&mut buf).unwrap_err(), Error::ExcessData);
+    /// let (len, _fds) = reader.read_socket_into(&mut buf).unwrap();
+    /// assert_eq!(&buf[..len], &small.data()[..]);
+    ///
+    /// // The same goes for messages already received, e.g. during the handshake.
+    /// let (a, _b) = UnixStream::pair().unwrap();
+    /// let a_fd = a.as_raw_fd();
+    /// let received = [&large.data()[..], &small.data()[..], &small.data()[..]].concat();
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, received, false, a_fd);
+    ///
+    /// assert_eq!(reader.read_socket_into(&mut buf).unwrap_err(), Error::ExcessData);
+    /// for _ in 0..2 {
+    ///     let (len, _fds) = reader.read_socket_into(&mut buf).unwrap();
+    ///     assert_eq!(&buf[..len], &small.data()[..]);
+    /// }
+    ///
+    /// // A header declaring a body of 1 GiB, beyond the maximum message size.
+    /// let (a, _b) = UnixStream::pair().unwrap();
+    /// let a_fd = a.as_raw_fd();
+    /// let mut header = vec![b'l', 1, 0, 1];
+    /// header.extend((1u32 << 30).to_le_bytes());
+    /// header.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+    /// let (read, write) = BoxedSplit::from(a).take();
+    /// let (_, mut reader) = Connection::from_halves(write, read, header, false, a_fd);
+    ///
+    /// assert_eq!(reader.read_socket_into(&mut buf).unwrap_err(), Error::ExcessData);
+    /// ```
+    ///
+    /// [`Message::from_bytes`]: crate::Message::from_bytes
+    pub fn read_socket_into(&mut self, buf: &mut [u8]) -> crate::Result<(usize, Vec<OwnedFd>)> {
+        if let Some(e) = self.pending_error.take() {
+            return Err(e);
+        }
+        if self.wait_wakeup()? {
+            return Err(crate::Error::Cancelled);
+        }
+        if buf.len() < MIN_MESSAGE_SIZE {
+            return Err(crate::Error::ExcessData);
+        }
+
+        let mut fds = vec![];
+        self.fill(&mut buf[..MIN_MESSAGE_SIZE], &mut fds, 0, None)?;
+        let pos = MIN_MESSAGE_SIZE;
+
+        let (primary_header, fields_len) = PrimaryHeader::read(buf)?;
+        let (_, total_len) = declared_message_len(fields_len, primary_header.body_len())?;
+        if total_len > MAX_MESSAGE_SIZE {
+            return Err(crate::Error::ExcessData);
+        }
+        if total_len > buf.len() {
+            // Skip the rest of the message, to stay in sync with the stream.
+            let mut remaining = total_len - pos;
+            while remaining > 0 {
+                let len = remaining.min(buf.len());
+                let offset = total_len - remaining;
+                self.fill(&mut buf[..len], &mut fds, offset, Some(total_len))?;
+                remaining -= len;
+            }
+
+            return Err(crate::Error::ExcessData);
+        }
+
+        self.fill(&mut buf[pos..total_len], &mut fds, pos, Some(total_len))?;
+        self.stats.messages += 1;
+
+        Ok((total_len, fds))
+    }
+
+    // Fill `buf` with the bytes already received first, and the rest from the socket. See
+    // `recv_exact` for the arguments.
+    fn fill(
+        &mut self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+        offset: usize,
+        expected: Option<usize>,
+    ) -> crate::Result<()> {
+        let mut pos = 0;
+        if let Some(mut received) = self.already_received_bytes.take() {
+            pos = received.len().min(buf.len());
+            buf[..pos].copy_from_slice(&received[..pos]);
+            received.drain(..pos);
+            if !received.is_empty() {
+                self.already_received_bytes = Some(received);
+            }
+        }
+
+        self.recv_exact(&mut buf[pos..], fds, offset + pos, expected)
+    }
+
+    // Receive exactly `buf.len()` bytes, adding the received FDs to `fds`. `buf` starts at
+    // `offset` in a message of length `expected`, if known, for reporting a truncated message.
+    fn recv_exact(
+        &mut self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+        offset: usize,
+        expected: Option<usize>,
+    ) -> crate::Result<()> {
+        let mut pos = 0;
+        while pos < buf.len() {
+            // The unix socket reports the end of the stream as a broken pipe.
+            let len = match self.recvmsg(&mut buf[pos..]) {
+                Ok((len, received)) => {
+                    fds.extend(received);
+                    len
+                }
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+                Err(e) => return Err(e.into()),
             };
-            pos += read;
-            if read == 0 {
+            if len == 0 {
+                if offset + pos > 0 {
+                    return Err(crate::Error::TruncatedMessage {
+                        read: offset + pos,
+                        expected,
+                    });
+                }
+
                 return Err(crate::Error::InputOutput(
                     std::io::Error::new(
                         std::io::ErrorKind::UnexpectedEof,
@@ -90,14 +674,26 @@ impl SocketReader {
                     .into(),
                 ));
             }
+            pos += len;
         }
 
-        // If we reach here, the message is complete; return it
-        let seq = self.prev_seq + 1;
-        self.prev_seq = seq;
-        let endian = Endian::from(primary_header.endian_sig());
-        let ctxt = Context::new_dbus(endian, 0);
-        let bytes = serialized::Data::new_fds(bytes, ctxt, fds);
-        Message::from_raw_parts(bytes, seq)
+        Ok(())
+    }
+
+    fn recvmsg(&mut self, buf: &mut [u8]) -> io::Result<(usize, Vec<OwnedFd>)> {
+        self.stats.recvmsg_calls += 1;
+        let res = self.socket.recvmsg(buf)?;
+        self.stats.bytes_read += res.0 as u64;
+        self.stats.fds_received += res.1.len() as u64;
+
+        Ok(res)
     }
 }
+
+// Whether `fd` is in nonblocking mode.
+fn is_nonblocking(fd: RawFd) -> bool {
+    // SAFETY: `F_GETFL` only queries the flags of the file descriptor.
+    let flags = unsafe { nix::libc::fcntl(fd, nix::libc::F_GETFL) };
+
+    flags != -1 && flags & nix::libc::O_NONBLOCK != 0
+}
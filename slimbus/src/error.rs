@@ -58,6 +58,35 @@ pub enum Error {
     MissingParameter(&'static str),
     /// Serial number in the message header is 0 (which is invalid).
     InvalidSerial,
+    /// The signature of a type doesn't match the signature declared by a message.
+    SignatureMismatch {
+        /// The signature of the type.
+        expected: String,
+        /// The signature declared by the message.
+        actual: String,
+    },
+    /// A blocking read was cancelled through a [`crate::connection::WakeupHandle`].
+    Cancelled,
+    /// The peer closed the connection in the middle of a message.
+    ///
+    /// A connection closed between two messages is reported as an [`Error::InputOutput`] error of
+    /// kind [`io::ErrorKind::UnexpectedEof`] instead.
+    TruncatedMessage {
+        /// The number of bytes of the message received before the end of the stream.
+        read: usize,
+        /// The length of the message, or `None` if the end of the stream came before the fixed
+        /// part of the header, which gives it.
+        expected: Option<usize>,
+    },
+    /// The number of file descriptors given doesn't match the number the signature refers to.
+    FdCountMismatch {
+        /// The number of file descriptors the signature refers to.
+        expected: usize,
+        /// The number of file descriptors given.
+        actual: usize,
+    },
+    /// Invalid hexadecimal encoding of a message.
+    InvalidHex(hex::FromHexError),
 }
 
 impl PartialEq for Error {
@@ -73,6 +102,17 @@ impl PartialEq for Error {
             (Self::MissingField, Self::MissingField) => true,
             (Self::InvalidGUID, Self::InvalidGUID) => true,
             (Self::InvalidSerial, Self::InvalidSerial) => true,
+            (Self::Cancelled, Self::Cancelled) => true,
+            (
+                Self::TruncatedMessage {
+                    read: r1,
+                    expected: e1,
+                },
+                Self::TruncatedMessage {
+                    read: r2,
+                    expected: e2,
+                },
+            ) => r1 == r2 && e1 == e2,
             (Self::Unsupported, Self::Unsupported) => true,
             (Self::FDO(s), Self::FDO(o)) => s == o,
             (Self::InvalidField, Self::InvalidField) => true,
@@ -82,6 +122,27 @@ impl PartialEq for Error {
             (Self::NameTaken, Self::NameTaken) => true,
             (Error::InputOutput(_), Self::InputOutput(_)) => false,
             (Self::Failure(s1), Self::Failure(s2)) => s1 == s2,
+            (
+                Self::SignatureMismatch {
+                    expected: e1,
+                    actual: a1,
+                },
+                Self::SignatureMismatch {
+                    expected: e2,
+                    actual: a2,
+                },
+            ) => e1 == e2 && a1 == a2,
+            (
+                Self::FdCountMismatch {
+                    expected: e1,
+                    actual: a1,
+                },
+                Self::FdCountMismatch {
+                    expected: e2,
+                    actual: a2,
+                },
+            ) => e1 == e2 && a1 == a2,
+            (Self::InvalidHex(s), Self::InvalidHex(o)) => s == o,
             (_, _) => false,
         }
     }
@@ -110,6 +171,11 @@ impl error::Error for Error {
             Error::Failure(_) => None,
             Error::MissingParameter(_) => None,
             Error::InvalidSerial => None,
+            Error::SignatureMismatch { .. } => None,
+            Error::Cancelled => None,
+            Error::TruncatedMessage { .. } => None,
+            Error::FdCountMismatch { .. } => None,
+            Error::InvalidHex(e) => Some(e),
         }
     }
 }
@@ -144,6 +210,30 @@ impl fmt::Display for Error {
                 write!(f, "Parameter `{}` was not specified but it is required", p)
             }
             Error::InvalidSerial => write!(f, "Serial number in the message header is 0"),
+            Error::SignatureMismatch { expected, actual } => write!(
+                f,
+                "Signature mismatch: expected `{expected}`, message has `{actual}`"
+            ),
+            Error::Cancelled => write!(f, "Read cancelled"),
+            Error::TruncatedMessage {
+                read,
+                expected: Some(expected),
+            } => write!(
+                f,
+                "Peer disconnected after {read}/{expected} bytes of a message"
+            ),
+            Error::TruncatedMessage {
+                read,
+                expected: None,
+            } => write!(
+                f,
+                "Peer disconnected after {read} bytes of a message header"
+            ),
+            Error::FdCountMismatch { expected, actual } => write!(
+                f,
+                "Signature refers to {expected} file descriptors, but {actual} were given"
+            ),
+            Error::InvalidHex(e) => write!(f, "invalid hexadecimal message: {e}"),
         }
     }
 }
@@ -173,6 +263,20 @@ impl Clone for Error {
             Error::Failure(e) => Error::Failure(e.clone()),
             Error::MissingParameter(p) => Error::MissingParameter(p),
             Error::InvalidSerial => Error::InvalidSerial,
+            Error::SignatureMismatch { expected, actual } => Error::SignatureMismatch {
+                expected: expected.clone(),
+                actual: actual.clone(),
+            },
+            Error::Cancelled => Error::Cancelled,
+            Error::TruncatedMessage { read, expected } => Error::TruncatedMessage {
+                read: *read,
+                expected: *expected,
+            },
+            Error::FdCountMismatch { expected, actual } => Error::FdCountMismatch {
+                expected: *expected,
+                actual: *actual,
+            },
+            Error::InvalidHex(e) => Error::InvalidHex(*e),
         }
     }
 }
@@ -231,7 +335,11 @@ impl From<Message> for Error {
 
         if let Some(name) = header.error_name() {
             let name = name.to_owned().into();
-            match message.body().deserialize_unchecked::<&str>() {
+            let body = message.body();
+            if body.is_empty() {
+                return Error::MethodError(name, None, message);
+            }
+            match body.deserialize_unchecked::<&str>() {
                 Ok(detail) => Error::MethodError(name, Some(String::from(detail)), message),
                 Err(_) => Error::MethodError(name, None, message),
             }
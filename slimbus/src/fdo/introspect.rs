@@ -0,0 +1,652 @@
+//! Introspection data of D-Bus objects.
+//!
+//! These types describe the interfaces implemented by an object, as returned by the
+//! `org.freedesktop.DBus.Introspectable.Introspect` method. See the [introspection data format]
+//! in the D-Bus specification for details.
+//!
+//! [introspection data format]: https://dbus.freedesktop.org/doc/dbus-specification.html#introspection-format
+
+use zbus_names::{BusName, InterfaceName, MemberName, OwnedInterfaceName, OwnedMemberName};
+use zvariant::{ObjectPath, OwnedSignature, Signature};
+
+use crate::{
+    fdo,
+    message::{Builder, Type},
+    Error, Message, Result,
+};
+
+/// The direction of a method argument.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ArgDirection {
+    /// An input argument, passed by the caller. This is the default for method arguments.
+    #[default]
+    In,
+    /// An output argument, part of the method reply.
+    Out,
+}
+
+/// An argument of a method or signal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Arg {
+    name: Option<String>,
+    ty: OwnedSignature,
+    direction: Option<ArgDirection>,
+}
+
+impl Arg {
+    /// Create a new `Arg` of the given type.
+    pub fn new<'s, S>(ty: S) -> Result<Self>
+    where
+        S: TryInto<Signature<'s>>,
+        S::Error: Into<Error>,
+    {
+        Ok(Self {
+            name: None,
+            ty: ty.try_into().map_err(Into::into)?.to_owned().into(),
+            direction: None,
+        })
+    }
+
+    /// Set the name of the argument.
+    pub fn set_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+
+        self
+    }
+
+    /// Set the direction of the argument.
+    pub fn set_direction(mut self, direction: ArgDirection) -> Self {
+        self.direction = Some(direction);
+
+        self
+    }
+
+    /// The name of the argument, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The type signature of the argument.
+    pub fn ty(&self) -> &Signature<'static> {
+        &self.ty
+    }
+
+    /// The direction of the argument, if specified.
+    pub fn direction(&self) -> Option<ArgDirection> {
+        self.direction
+    }
+}
+
+/// A method of an [`Interface`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Method {
+    name: OwnedMemberName,
+    args: Vec<Arg>,
+    // Set when added to an `Interface`.
+    interface: Option<OwnedInterfaceName>,
+}
+
+impl Method {
+    /// Create a new `Method` without arguments.
+    pub fn new<'m, M>(name: M) -> Result<Self>
+    where
+        M: TryInto<MemberName<'m>>,
+        M::Error: Into<Error>,
+    {
+        Ok(Self {
+            name: name.try_into().map_err(Into::into)?.to_owned().into(),
+            args: vec![],
+            interface: None,
+        })
+    }
+
+    fn standard(name: &'static str) -> Self {
+        Self::new(name).expect("valid member name")
+    }
+
+    /// Add an argument.
+    pub fn add_arg(mut self, arg: Arg) -> Self {
+        self.args.push(arg);
+
+        self
+    }
+
+    /// The name of the method.
+    pub fn name(&self) -> &MemberName<'static> {
+        &self.name
+    }
+
+    /// The arguments of the method.
+    pub fn args(&self) -> &[Arg] {
+        &self.args
+    }
+
+    /// The signature of the body of a call to this method, i.e the types of its input arguments.
+    pub fn in_signature(&self) -> String {
+        self.args
+            .iter()
+            .filter(|arg| arg.direction().unwrap_or_default() == ArgDirection::In)
+            .map(|arg| arg.ty().as_str())
+            .collect()
+    }
+
+    /// Create a builder for a call to this method on the object at `path` of `destination`.
+    ///
+    /// The member is set to the name of the method and, if the method was added to an
+    /// [`Interface`], the interface to its name. Only the arguments remain to be passed to
+    /// [`Builder::build`].
+    pub fn call_builder<'b, 'p: 'b, 'd: 'b, P, D>(
+        &'b self,
+        path: P,
+        destination: D,
+    ) -> Result<Builder<'b>>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        D: TryInto<BusName<'d>>,
+        P::Error: Into<Error>,
+        D::Error: Into<Error>,
+    {
+        let builder = Message::method(path, self.name().clone())?.destination(destination)?;
+        match &self.interface {
+            Some(interface) => builder.interface(interface.clone().into_inner()),
+            None => Ok(builder),
+        }
+    }
+
+    /// Check that the body of the method call `msg` matches the input arguments of this method.
+    ///
+    /// Returns [`Error::SignatureMismatch`] if it doesn't.
+    pub fn validate_call(&self, msg: &Message) -> Result<()> {
+        let expected = self.in_signature();
+        let body = msg.body();
+        let actual = body.signature();
+        let actual = actual.as_ref().map(|s| s.as_str()).unwrap_or_default();
+        if expected != actual {
+            return Err(Error::SignatureMismatch {
+                expected,
+                actual: actual.to_owned(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// A signal of an [`Interface`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signal {
+    name: OwnedMemberName,
+    args: Vec<Arg>,
+}
+
+impl Signal {
+    /// Create a new `Signal` without arguments.
+    pub fn new<'m, M>(name: M) -> Result<Self>
+    where
+        M: TryInto<MemberName<'m>>,
+        M::Error: Into<Error>,
+    {
+        Ok(Self {
+            name: name.try_into().map_err(Into::into)?.to_owned().into(),
+            args: vec![],
+        })
+    }
+
+    /// Add an argument.
+    ///
+    /// The direction of signal arguments is always out, any other direction is ignored.
+    pub fn add_arg(mut self, arg: Arg) -> Self {
+        self.args.push(arg);
+
+        self
+    }
+
+    /// The name of the signal.
+    pub fn name(&self) -> &MemberName<'static> {
+        &self.name
+    }
+
+    /// The arguments of the signal.
+    pub fn args(&self) -> &[Arg] {
+        &self.args
+    }
+}
+
+/// The access permissions of a [`Property`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PropertyAccess {
+    /// The property can only be read.
+    Read,
+    /// The property can only be written.
+    Write,
+    /// The property can be read and written.
+    ReadWrite,
+}
+
+impl PropertyAccess {
+    /// The value of the `access` attribute of the property in introspection XML.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PropertyAccess::Read => "read",
+            PropertyAccess::Write => "write",
+            PropertyAccess::ReadWrite => "readwrite",
+        }
+    }
+}
+
+/// A property of an [`Interface`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Property {
+    name: String,
+    ty: OwnedSignature,
+    access: PropertyAccess,
+}
+
+impl Property {
+    /// Create a new `Property` of the given type.
+    pub fn new<'s, S>(name: &str, ty: S, access: PropertyAccess) -> Result<Self>
+    where
+        S: TryInto<Signature<'s>>,
+        S::Error: Into<Error>,
+    {
+        Ok(Self {
+            name: name.to_owned(),
+            ty: ty.try_into().map_err(Into::into)?.to_owned().into(),
+            access,
+        })
+    }
+
+    /// The name of the property.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The type signature of the property.
+    pub fn ty(&self) -> &Signature<'static> {
+        &self.ty
+    }
+
+    /// The access permissions of the property.
+    pub fn access(&self) -> PropertyAccess {
+        self.access
+    }
+}
+
+/// An interface implemented by a [`Node`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interface {
+    name: OwnedInterfaceName,
+    methods: Vec<Method>,
+    signals: Vec<Signal>,
+    properties: Vec<Property>,
+}
+
+impl Interface {
+    /// Create a new `Interface` without members.
+    pub fn new<'i, I>(name: I) -> Result<Self>
+    where
+        I: TryInto<InterfaceName<'i>>,
+        I::Error: Into<Error>,
+    {
+        Ok(Self {
+            name: name.try_into().map_err(Into::into)?.to_owned().into(),
+            methods: vec![],
+            signals: vec![],
+            properties: vec![],
+        })
+    }
+
+    /// The standard `org.freedesktop.DBus.Peer` interface.
+    pub fn standard_peer() -> Self {
+        let out = |ty: &'static str, name| {
+            Arg::new(ty)
+                .expect("valid signature")
+                .set_name(name)
+                .set_direction(ArgDirection::Out)
+        };
+
+        Self::standard("org.freedesktop.DBus.Peer")
+            .add_method(Method::standard("Ping"))
+            .add_method(Method::standard("GetMachineId").add_arg(out("s", "machine_uuid")))
+    }
+
+    /// The standard `org.freedesktop.DBus.Introspectable` interface.
+    pub fn standard_introspectable() -> Self {
+        let out = Arg::new("s")
+            .expect("valid signature")
+            .set_name("xml_data")
+            .set_direction(ArgDirection::Out);
+
+        Self::standard("org.freedesktop.DBus.Introspectable")
+            .add_method(Method::standard("Introspect").add_arg(out))
+    }
+
+    /// The standard `org.freedesktop.DBus.Properties` interface.
+    pub fn standard_properties() -> Self {
+        let arg = |ty: &'static str, name| Arg::new(ty).expect("valid signature").set_name(name);
+        let out = |ty, name| arg(ty, name).set_direction(ArgDirection::Out);
+
+        Self::standard("org.freedesktop.DBus.Properties")
+            .add_method(
+                Method::standard("Get")
+                    .add_arg(arg("s", "interface_name"))
+                    .add_arg(arg("s", "property_name"))
+                    .add_arg(out("v", "value")),
+            )
+            .add_method(
+                Method::standard("GetAll")
+                    .add_arg(arg("s", "interface_name"))
+                    .add_arg(out("a{sv}", "properties")),
+            )
+            .add_method(
+                Method::standard("Set")
+                    .add_arg(arg("s", "interface_name"))
+                    .add_arg(arg("s", "property_name"))
+                    .add_arg(arg("v", "value")),
+            )
+            .add_signal(
+                Signal::new("PropertiesChanged")
+                    .expect("valid member name")
+                    .add_arg(arg("s", "interface_name"))
+                    .add_arg(arg("a{sv}", "changed_properties"))
+                    .add_arg(arg("as", "invalidated_properties")),
+            )
+    }
+
+    fn standard(name: &'static str) -> Self {
+        Self::new(name).expect("valid interface name")
+    }
+
+    /// Add a method.
+    pub fn add_method(mut self, mut method: Method) -> Self {
+        method.interface = Some(self.name.clone());
+        self.methods.push(method);
+
+        self
+    }
+
+    /// The name of the interface.
+    pub fn name(&self) -> &InterfaceName<'static> {
+        &self.name
+    }
+
+    /// Add a signal.
+    pub fn add_signal(mut self, signal: Signal) -> Self {
+        self.signals.push(signal);
+
+        self
+    }
+
+    /// Add a property.
+    pub fn add_property(mut self, property: Property) -> Self {
+        self.properties.push(property);
+
+        self
+    }
+
+    /// The methods of the interface.
+    pub fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// The signals of the interface.
+    pub fn signals(&self) -> &[Signal] {
+        &self.signals
+    }
+
+    /// The properties of the interface.
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Look up a method by name.
+    pub fn method(&self, name: &str) -> Option<&Method> {
+        self.methods.iter().find(|m| m.name().as_str() == name)
+    }
+}
+
+/// An object and the interfaces it implements.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Node {
+    name: Option<String>,
+    interfaces: Vec<Interface>,
+    nodes: Vec<Node>,
+}
+
+impl Node {
+    /// Create a new empty `Node`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the name (relative object path) of the node.
+    pub fn set_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+
+        self
+    }
+
+    /// Add an interface.
+    pub fn add_interface(mut self, interface: Interface) -> Self {
+        self.interfaces.push(interface);
+
+        self
+    }
+
+    /// Add the standard `Peer` and `Introspectable` interfaces, as well as `Properties` if
+    /// `properties` is `true`.
+    pub fn add_standard_interfaces(mut self, properties: bool) -> Self {
+        self = self
+            .add_interface(Interface::standard_peer())
+            .add_interface(Interface::standard_introspectable());
+        if properties {
+            self = self.add_interface(Interface::standard_properties());
+        }
+
+        self
+    }
+
+    /// Add a child node.
+    pub fn add_node(mut self, node: Node) -> Self {
+        self.nodes.push(node);
+
+        self
+    }
+
+    /// The name of the node, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The interfaces of the node.
+    pub fn interfaces(&self) -> &[Interface] {
+        &self.interfaces
+    }
+
+    /// The child nodes.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Look up an interface by name.
+    pub fn interface(&self, name: &str) -> Option<&Interface> {
+        self.interfaces.iter().find(|i| i.name().as_str() == name)
+    }
+
+    /// The introspection XML document describing this node.
+    ///
+    /// The document starts with the DOCTYPE declaration of the introspection format, followed by
+    /// the node element with its interfaces and child nodes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::fdo::introspect::*;
+    /// # (|| -> slimbus::Result<()> {
+    /// let node = Node::new()
+    ///     .add_interface(
+    ///         Interface::new("org.example.Calculator")?
+    ///             .add_method(
+    ///                 Method::new("Add")?
+    ///                     .add_arg(Arg::new("u")?.set_name("a"))
+    ///                     .add_arg(Arg::new("u")?.set_name("b"))
+    ///                     .add_arg(Arg::new("u")?.set_direction(ArgDirection::Out)),
+    ///             )
+    ///             .add_signal(Signal::new("Overflow")?.add_arg(Arg::new("s")?))
+    ///             .add_property(Property::new("Precision", "u", PropertyAccess::ReadWrite)?),
+    ///     )
+    ///     .add_node(Node::new().set_name("child"));
+    ///
+    /// assert!(node.to_xml().ends_with(
+    ///     r#"<node>
+    ///   <interface name="org.example.Calculator">
+    ///     <method name="Add">
+    ///       <arg name="a" type="u" direction="in"/>
+    ///       <arg name="b" type="u" direction="in"/>
+    ///       <arg type="u" direction="out"/>
+    ///     </method>
+    ///     <signal name="Overflow">
+    ///       <arg type="s"/>
+    ///     </signal>
+    ///     <property name="Precision" type="u" access="readwrite"/>
+    ///   </interface>
+    ///   <node name="child"/>
+    /// </node>
+    /// "#
+    /// ));
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<!DOCTYPE node PUBLIC \"-//freedesktop//DTD D-BUS Object Introspection 1.0//EN\"\n \
+             \"http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd\">\n",
+        );
+        self.write_xml(&mut xml, 0);
+
+        xml
+    }
+
+    fn write_xml(&self, xml: &mut String, level: usize) {
+        let indent = "  ".repeat(level);
+        xml.push_str(&indent);
+        xml.push_str("<node");
+        if let Some(name) = self.name() {
+            write_attr(xml, "name", name);
+        }
+        if self.interfaces.is_empty() && self.nodes.is_empty() {
+            xml.push_str("/>\n");
+            return;
+        }
+        xml.push_str(">\n");
+
+        for interface in &self.interfaces {
+            xml.push_str(&indent);
+            xml.push_str("  <interface");
+            write_attr(xml, "name", interface.name());
+            xml.push_str(">\n");
+            for method in &interface.methods {
+                write_member(xml, &indent, "method", method.name(), &method.args, true);
+            }
+            for signal in &interface.signals {
+                write_member(xml, &indent, "signal", signal.name(), &signal.args, false);
+            }
+            for property in &interface.properties {
+                xml.push_str(&indent);
+                xml.push_str("    <property");
+                write_attr(xml, "name", property.name());
+                write_attr(xml, "type", property.ty());
+                write_attr(xml, "access", property.access().as_str());
+                xml.push_str("/>\n");
+            }
+            xml.push_str(&indent);
+            xml.push_str("  </interface>\n");
+        }
+        for node in &self.nodes {
+            node.write_xml(xml, level + 1);
+        }
+
+        xml.push_str(&indent);
+        xml.push_str("</node>\n");
+    }
+
+    /// Check that the method call `msg` matches a method declared by this node.
+    ///
+    /// The method is looked up using the interface and member fields of `msg`. If the node has no
+    /// such method, [`fdo::Error::UnknownInterface`] or [`fdo::Error::UnknownMethod`] is returned.
+    /// If the body doesn't match the input arguments of the method, [`Error::SignatureMismatch`]
+    /// is returned.
+    pub fn validate_call(&self, msg: &Message) -> Result<()> {
+        let header = msg.header();
+        if header.message_type() != Type::MethodCall {
+            return Err(Error::InvalidField);
+        }
+        let iface = header.interface().ok_or(Error::MissingField)?;
+        let member = header.member().ok_or(Error::MissingField)?;
+
+        let method = self
+            .interface(iface)
+            .ok_or_else(|| fdo::Error::UnknownInterface(format!("Unknown interface `{iface}`")))?
+            .method(member)
+            .ok_or_else(|| fdo::Error::UnknownMethod(format!("Unknown method `{member}`")))?;
+
+        method.validate_call(msg)
+    }
+}
+
+// Write a method or signal element, at the level of interface members.
+fn write_member(
+    xml: &mut String,
+    indent: &str,
+    tag: &str,
+    name: &str,
+    args: &[Arg],
+    with_direction: bool,
+) {
+    xml.push_str(indent);
+    xml.push_str("    <");
+    xml.push_str(tag);
+    write_attr(xml, "name", name);
+    if args.is_empty() {
+        xml.push_str("/>\n");
+        return;
+    }
+    xml.push_str(">\n");
+    for arg in args {
+        xml.push_str(indent);
+        xml.push_str("      <arg");
+        if let Some(name) = arg.name() {
+            write_attr(xml, "name", name);
+        }
+        write_attr(xml, "type", arg.ty());
+        if with_direction {
+            let direction = match arg.direction().unwrap_or_default() {
+                ArgDirection::In => "in",
+                ArgDirection::Out => "out",
+            };
+            write_attr(xml, "direction", direction);
+        }
+        xml.push_str("/>\n");
+    }
+    xml.push_str(indent);
+    xml.push_str("    </");
+    xml.push_str(tag);
+    xml.push_str(">\n");
+}
+
+// Write the attribute `name` with the given value, escaped.
+fn write_attr(xml: &mut String, name: &str, value: &str) {
+    xml.push(' ');
+    xml.push_str(name);
+    xml.push_str("=\"");
+    for c in value.chars() {
+        match c {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            '\'' => xml.push_str("&apos;"),
+            c => xml.push(c),
+        }
+    }
+    xml.push('"');
+}
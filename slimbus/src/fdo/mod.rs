@@ -0,0 +1,853 @@
+//! D-Bus standard interfaces.
+//!
+//! The D-Bus specification defines the message bus messages and some standard interfaces that may
+//! be useful across various D-Bus applications. This module provides their proxy.
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    os::unix::ffi::OsStringExt,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use zbus_names::{BusName, ErrorName, OwnedBusName, OwnedUniqueName};
+use zvariant::{DeserializeDict, ObjectPath, OwnedObjectPath, OwnedValue, SerializeDict, Type};
+
+use crate::{
+    message::{self, Header},
+    Address, DBusError, Message,
+};
+
+pub mod introspect;
+
+/// Credentials of a process connected to a bus server.
+///
+/// If unable to determine certain credentials (for instance, because the process is not on the same
+/// machine as the bus daemon, or because this version of the bus daemon does not support a
+/// particular security framework), or if the values of those credentials cannot be represented as
+/// documented here, then those credentials are omitted.
+///
+/// **Note**: unknown keys, in particular those with "." that are not from the specification, will
+/// be ignored. Use your own implementation or contribute your keys here, or in the specification.
+#[derive(Debug, Default, DeserializeDict, PartialEq, Eq, SerializeDict, Type)]
+#[zvariant(signature = "a{sv}")]
+pub struct ConnectionCredentials {
+    #[zvariant(rename = "UnixUserID")]
+    pub(crate) unix_user_id: Option<u32>,
+
+    #[zvariant(rename = "UnixGroupIDs")]
+    pub(crate) unix_group_ids: Option<Vec<u32>>,
+
+    #[zvariant(rename = "ProcessID")]
+    pub(crate) process_id: Option<u32>,
+
+    #[zvariant(rename = "WindowsSID")]
+    pub(crate) windows_sid: Option<String>,
+
+    #[zvariant(rename = "LinuxSecurityLabel")]
+    pub(crate) linux_security_label: Option<Vec<u8>>,
+}
+
+impl ConnectionCredentials {
+    /// The numeric Unix user ID, as defined by POSIX.
+    pub fn unix_user_id(&self) -> Option<u32> {
+        self.unix_user_id
+    }
+
+    /// The numeric Unix group IDs (including both the primary group and the supplementary groups),
+    /// as defined by POSIX, in numerically sorted order. This array is either complete or absent:
+    /// if the message bus is able to determine some but not all of the caller's groups, or if one
+    /// of the groups is not representable in a UINT32, it must not add this credential to the
+    /// dictionary.
+    pub fn unix_group_ids(&self) -> Option<&Vec<u32>> {
+        self.unix_group_ids.as_ref()
+    }
+
+    /// Same as [`ConnectionCredentials::unix_group_ids`], but consumes `self` and returns the group
+    /// IDs Vec.
+    pub fn into_unix_group_ids(self) -> Option<Vec<u32>> {
+        self.unix_group_ids
+    }
+
+    /// The numeric process ID, on platforms that have this concept. On Unix, this is the process ID
+    /// defined by POSIX.
+    pub fn process_id(&self) -> Option<u32> {
+        self.process_id
+    }
+
+    /// The Windows security identifier in its string form, e.g.
+    /// `S-1-5-21-3623811015-3361044348-30300820-1013` for a domain or local computer user or
+    /// "S-1-5-18` for the LOCAL_SYSTEM user.
+    pub fn windows_sid(&self) -> Option<&String> {
+        self.windows_sid.as_ref()
+    }
+
+    /// Same as [`ConnectionCredentials::windows_sid`], but consumes `self` and returns the SID
+    /// string.
+    pub fn into_windows_sid(self) -> Option<String> {
+        self.windows_sid
+    }
+
+    /// On Linux systems, the security label that would result from the SO_PEERSEC getsockopt call.
+    /// The array contains the non-zero bytes of the security label in an unspecified
+    /// ASCII-compatible encoding, followed by a single zero byte.
+    ///
+    /// For example, the SELinux context `system_u:system_r:init_t:s0` (a string of length 27) would
+    /// be encoded as 28 bytes ending with `':', 's', '0', '\x00'`
+    ///
+    /// On SELinux systems this is the SELinux context, as output by `ps -Z` or `ls -Z`. Typical
+    /// values might include `system_u:system_r:init_t:s0`,
+    /// `unconfined_u:unconfined_r:unconfined_t:s0-s0:c0.c1023`, or
+    /// `unconfined_u:unconfined_r:chrome_sandbox_t:s0-s0:c0.c1023`.
+    ///
+    /// On Smack systems, this is the Smack label. Typical values might include `_`, `*`, `User`,
+    /// `System` or `System::Shared`.
+    ///
+    /// On AppArmor systems, this is the AppArmor context, a composite string encoding the AppArmor
+    /// label (one or more profiles) and the enforcement mode. Typical values might include
+    /// `unconfined`, `/usr/bin/firefox (enforce)` or `user1 (complain)`.
+    pub fn linux_security_label(&self) -> Option<&Vec<u8>> {
+        self.linux_security_label.as_ref()
+    }
+
+    /// Same as [`ConnectionCredentials::linux_security_label`], but consumes `self` and returns
+    /// the security label bytes.
+    pub fn into_linux_security_label(self) -> Option<Vec<u8>> {
+        self.linux_security_label
+    }
+
+    /// Set the numeric Unix user ID, as defined by POSIX.
+    pub fn set_unix_user_id(mut self, unix_user_id: u32) -> Self {
+        self.unix_user_id = Some(unix_user_id);
+
+        self
+    }
+
+    /// Add a numeric Unix group ID.
+    ///
+    /// See [`ConnectionCredentials::unix_group_ids`] for more information.
+    pub fn add_unix_group_id(mut self, unix_group_id: u32) -> Self {
+        self.unix_group_ids
+            .get_or_insert_with(Vec::new)
+            .push(unix_group_id);
+
+        self
+    }
+
+    /// Set the numeric process ID, on platforms that have this concept.
+    ///
+    /// See [`ConnectionCredentials::process_id`] for more information.
+    pub fn set_process_id(mut self, process_id: u32) -> Self {
+        self.process_id = Some(process_id);
+
+        self
+    }
+
+    /// Set the Windows security identifier in its string form.
+    pub fn set_windows_sid(mut self, windows_sid: String) -> Self {
+        self.windows_sid = Some(windows_sid);
+
+        self
+    }
+
+    /// Set the Linux security label.
+    ///
+    /// See [`ConnectionCredentials::linux_security_label`] for more information.
+    pub fn set_linux_security_label(mut self, linux_security_label: Vec<u8>) -> Self {
+        self.linux_security_label = Some(linux_security_label);
+
+        self
+    }
+}
+
+/// A signal emitted by the message bus itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DBusSignal {
+    /// The owner of a name changed.
+    ///
+    /// An owner of `None` means the name had no owner before, or has none anymore.
+    NameOwnerChanged {
+        /// The name whose owner changed.
+        name: OwnedBusName,
+        /// The previous owner.
+        old_owner: Option<OwnedUniqueName>,
+        /// The new owner.
+        new_owner: Option<OwnedUniqueName>,
+    },
+    /// This connection acquired ownership of a name.
+    NameAcquired(OwnedBusName),
+    /// This connection lost ownership of a name.
+    NameLost(OwnedBusName),
+    /// The list of activatable services changed, e.g. because a service file was added.
+    ActivatableServicesChanged,
+}
+
+impl DBusSignal {
+    /// Recognize a signal from the `org.freedesktop.DBus` interface and decode its body.
+    ///
+    /// Returns `None` if `msg` is not a known signal of the bus, or if its body doesn't match.
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let header = msg.header();
+        if header.message_type() != message::Type::Signal
+            || header.interface()?.as_str() != "org.freedesktop.DBus"
+        {
+            return None;
+        }
+
+        let body = msg.body();
+        match header.member()?.as_str() {
+            "NameOwnerChanged" => {
+                let (name, old_owner, new_owner): (OwnedBusName, &str, &str) =
+                    body.deserialize().ok()?;
+                let owner = |o: &str| match o {
+                    "" => Ok(None),
+                    o => OwnedUniqueName::try_from(o).map(Some),
+                };
+
+                Some(Self::NameOwnerChanged {
+                    name,
+                    old_owner: owner(old_owner).ok()?,
+                    new_owner: owner(new_owner).ok()?,
+                })
+            }
+            "NameAcquired" => body.deserialize().ok().map(Self::NameAcquired),
+            "NameLost" => body.deserialize().ok().map(Self::NameLost),
+            "ActivatableServicesChanged" if body.is_empty() => {
+                Some(Self::ActivatableServicesChanged)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The body of an `org.freedesktop.DBus.Properties.PropertiesChanged` signal.
+#[derive(Debug, Deserialize, PartialEq, Type)]
+pub struct PropertiesChanged {
+    interface: String,
+    changed: HashMap<String, OwnedValue>,
+    invalidated: Vec<String>,
+}
+
+impl PropertiesChanged {
+    /// Decode a `PropertiesChanged` signal.
+    ///
+    /// Returns `None` if `msg` is not a `PropertiesChanged` signal, or if its body doesn't match.
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let header = msg.header();
+        if header.message_type() != message::Type::Signal
+            || header.interface()?.as_str() != "org.freedesktop.DBus.Properties"
+            || header.member()?.as_str() != "PropertiesChanged"
+        {
+            return None;
+        }
+
+        msg.body().deserialize().ok()
+    }
+
+    /// The name of the interface the properties belong to.
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// The changed properties, with their new values.
+    pub fn changed(&self) -> &HashMap<String, OwnedValue> {
+        &self.changed
+    }
+
+    /// Same as [`PropertiesChanged::changed`], but consumes `self` and returns the changed
+    /// properties.
+    pub fn into_changed(self) -> HashMap<String, OwnedValue> {
+        self.changed
+    }
+
+    /// The properties that changed, but whose new values were not sent.
+    pub fn invalidated(&self) -> &[String] {
+        &self.invalidated
+    }
+}
+
+/// The objects, with their interfaces and properties, returned by [`get_managed_objects`].
+pub type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+/// Create a builder for an `org.freedesktop.DBus.ObjectManager.GetManagedObjects` call to the
+/// object manager at `path` of `destination`.
+///
+/// The call has no arguments, so build it with `()` as the body. Decode the reply with
+/// [`managed_objects`].
+pub fn get_managed_objects<'b, 'p: 'b, 'd: 'b, P, D>(
+    destination: D,
+    path: P,
+) -> crate::Result<message::Builder<'b>>
+where
+    P: TryInto<ObjectPath<'p>>,
+    D: TryInto<BusName<'d>>,
+    P::Error: Into<crate::Error>,
+    D::Error: Into<crate::Error>,
+{
+    Message::method(path, "GetManagedObjects")?
+        .destination(destination)?
+        .interface("org.freedesktop.DBus.ObjectManager")
+}
+
+/// Decode the reply to a [`get_managed_objects`] call.
+///
+/// An error reply is turned into an [`Error::MethodError`](crate::Error::MethodError).
+pub fn managed_objects(reply: &Message) -> crate::Result<ManagedObjects> {
+    reply_body(reply)
+}
+
+/// Create a builder for an `org.freedesktop.DBus.Properties.Get` call to the object at `path` of
+/// `destination`.
+///
+/// The body is `(interface_name, property_name)`, of signature `ss`. Decode the reply with
+/// [`property`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use slimbus::{fdo, Connection};
+/// # (|| -> slimbus::Result<()> {
+/// let (mut conn, mut reader, _) = Connection::session_with_hello()?;
+/// let call = fdo::get_property("org.freedesktop.DBus", "/org/freedesktop/DBus")?
+///     .build(&("org.freedesktop.DBus", "Features"))?;
+/// let features: Vec<String> = fdo::property(&conn.call_method(&mut reader, &call)?)?;
+/// # Ok(()) })().unwrap()
+/// ```
+pub fn get_property<'b, 'p: 'b, 'd: 'b, P, D>(
+    destination: D,
+    path: P,
+) -> crate::Result<message::Builder<'b>>
+where
+    P: TryInto<ObjectPath<'p>>,
+    D: TryInto<BusName<'d>>,
+    P::Error: Into<crate::Error>,
+    D::Error: Into<crate::Error>,
+{
+    Message::method(path, "Get")?
+        .destination(destination)?
+        .interface("org.freedesktop.DBus.Properties")
+}
+
+/// Decode the reply to a [`get_property`] call.
+///
+/// The property value is sent wrapped in a variant, which is unwrapped into `T`. If the value
+/// inside the variant isn't of the signature of `T`,
+/// [`Error::SignatureMismatch`](crate::Error::SignatureMismatch) is returned. An error reply is
+/// turned into an [`Error::MethodError`](crate::Error::MethodError).
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::{fdo, zvariant::Value, Error, Message};
+/// # (|| -> slimbus::Result<()> {
+/// let call = fdo::get_property("org.example.Service", "/org/example")?
+///     .build(&("org.example.Interface", "Volume"))?;
+/// let reply = Message::method_reply(&call)?.build(&Value::from(42u32))?;
+///
+/// assert_eq!(fdo::property::<u32>(&reply)?, 42);
+/// assert!(matches!(
+///     fdo::property::<String>(&reply),
+///     Err(Error::SignatureMismatch { expected, actual }) if expected == "s" && actual == "u"
+/// ));
+///
+/// // A body cut short after the signature of the value.
+/// let reply = unsafe { Message::method_reply(&call)?.build_raw_body(&[1, b'u'], "v", vec![]) }?;
+/// assert!(fdo::property::<u32>(&reply).is_err());
+/// # Ok(()) })().unwrap()
+/// ```
+pub fn property<T>(reply: &Message) -> crate::Result<T>
+where
+    T: serde::de::DeserializeOwned + Type,
+{
+    match reply.message_type() {
+        message::Type::MethodReturn => {}
+        message::Type::Error => return Err(reply.clone().into()),
+        _ => return Err(crate::Error::InvalidReply),
+    }
+
+    let body = reply.body();
+    let body_sig = body.signature();
+    if body_sig.as_deref() != Some("v") {
+        return Err(crate::Error::SignatureMismatch {
+            expected: "v".to_owned(),
+            actual: body_sig.map(|s| s.to_string()).unwrap_or_default(),
+        });
+    }
+
+    // A variant starts with the signature of its value: a length byte, the signature itself and a
+    // nul byte. The value follows, aligned as its type requires.
+    let data = body.data();
+    let sig_len = usize::from(
+        *data
+            .bytes()
+            .first()
+            .ok_or(crate::Error::Variant(zvariant::Error::OutOfBounds))?,
+    );
+    let value_sig = data
+        .bytes()
+        .get(1..1 + sig_len)
+        .ok_or(crate::Error::Variant(zvariant::Error::OutOfBounds))?;
+    let value_sig = zvariant::Signature::try_from(value_sig)?;
+    // The body may end right after the signature, without its nul byte.
+    let value_offset = sig_len + 2;
+    if data.len() < value_offset {
+        return Err(crate::Error::Variant(zvariant::Error::OutOfBounds));
+    }
+    let expected = T::signature();
+    if expected != value_sig {
+        return Err(crate::Error::SignatureMismatch {
+            expected: expected.to_string(),
+            actual: value_sig.to_string(),
+        });
+    }
+
+    data.slice(value_offset..)
+        .deserialize_for_signature(value_sig)
+        .map(|(value, _)| value)
+        .map_err(Into::into)
+}
+
+/// Create a builder for an `org.freedesktop.DBus.Containers1.AddServer` call to the message bus.
+///
+/// This asks the bus for a new server socket for a container instance, e.g. a sandboxed app,
+/// whose connections are confined. The body is `(container_type, app_id, metadata,
+/// named_arguments)`, of signature `ssa{sv}a{sv}`. Decode the reply with [`container_server`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::collections::HashMap;
+/// # use slimbus::{connection, fdo, zvariant::Value, Connection};
+/// # (|| -> slimbus::Result<()> {
+/// let (mut conn, mut reader, _) = Connection::session_with_hello()?;
+/// let metadata: HashMap<&str, Value<'_>> = HashMap::new();
+/// let named_arguments: HashMap<&str, Value<'_>> = HashMap::new();
+/// let call = fdo::add_container_server()?.build(&(
+///     "org.flatpak",
+///     "org.example.App",
+///     metadata,
+///     named_arguments,
+/// ))?;
+/// let server = fdo::container_server(&conn.call_method(&mut reader, &call)?)?;
+///
+/// // Connections to the container socket are confined to the container instance.
+/// let (confined, _reader) = connection::build(server.address()?)?;
+/// # Ok(()) })().unwrap()
+/// ```
+pub fn add_container_server<'b>() -> crate::Result<message::Builder<'b>> {
+    Message::method("/org/freedesktop/DBus", "AddServer")?
+        .destination("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus.Containers1")
+}
+
+/// The server socket of a container instance, as returned by an [`add_container_server`] call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerServer {
+    path: OwnedObjectPath,
+    socket_path: PathBuf,
+    address: String,
+}
+
+impl ContainerServer {
+    /// The object path of the container instance.
+    pub fn path(&self) -> &ObjectPath<'_> {
+        &self.path
+    }
+
+    /// The path of the listening socket, e.g. to bind-mount it in the container.
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// The address to connect to the container instance.
+    pub fn address(&self) -> crate::Result<Address> {
+        self.address.parse()
+    }
+}
+
+/// Decode the reply to an [`add_container_server`] call.
+///
+/// An error reply is turned into an [`Error::MethodError`](crate::Error::MethodError).
+pub fn container_server(reply: &Message) -> crate::Result<ContainerServer> {
+    let (path, mut socket_path, address): (OwnedObjectPath, Vec<u8>, String) = reply_body(reply)?;
+    // The socket path is a byte string, which is conventionally NUL-terminated.
+    if socket_path.last() == Some(&0) {
+        socket_path.pop();
+    }
+
+    Ok(ContainerServer {
+        path,
+        socket_path: PathBuf::from(OsString::from_vec(socket_path)),
+        address,
+    })
+}
+
+/// Create a builder for an `org.freedesktop.DBus.GetAdtAuditSessionData` call to the message bus.
+///
+/// The body is the bus name of the connection to get the Solaris ADT audit session data of, of
+/// signature `s`. Decode the reply with [`adt_audit_session_data`].
+pub fn get_adt_audit_session_data<'b>() -> crate::Result<message::Builder<'b>> {
+    Message::method("/org/freedesktop/DBus", "GetAdtAuditSessionData")?
+        .destination("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")
+}
+
+/// Decode the reply to a [`get_adt_audit_session_data`] call.
+///
+/// If the bus has no audit data for the connection, [`Error::AdtAuditDataUnknown`] is returned.
+/// Other error replies are turned into an [`Error::MethodError`](crate::Error::MethodError).
+pub fn adt_audit_session_data(reply: &Message) -> crate::Result<Vec<u8>> {
+    reply_body(reply).map_err(|e| {
+        typed_error(
+            e,
+            "org.freedesktop.DBus.Error.AdtAuditDataUnknown",
+            Error::AdtAuditDataUnknown,
+        )
+    })
+}
+
+/// Create a builder for an `org.freedesktop.DBus.GetConnectionSELinuxSecurityContext` call to the
+/// message bus.
+///
+/// The body is the bus name of the connection to get the SELinux security context of, of
+/// signature `s`. Decode the reply with [`selinux_security_context`].
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::{fdo, Error, Message};
+/// # (|| -> slimbus::Result<()> {
+/// let call = fdo::get_connection_selinux_security_context()?.build(&"org.example.Service")?;
+///
+/// // The reply of a bus without SELinux.
+/// let reply = Message::method_error(
+///     &call,
+///     "org.freedesktop.DBus.Error.SELinuxSecurityContextUnknown",
+/// )?
+/// .build(&"SELinux is not enabled")?;
+///
+/// let context = fdo::selinux_security_context(&reply);
+/// assert!(matches!(
+///     context,
+///     Err(Error::FDO(e)) if matches!(*e, fdo::Error::SELinuxSecurityContextUnknown(_))
+/// ));
+/// # Ok(()) })().unwrap()
+/// ```
+pub fn get_connection_selinux_security_context<'b>() -> crate::Result<message::Builder<'b>> {
+    Message::method(
+        "/org/freedesktop/DBus",
+        "GetConnectionSELinuxSecurityContext",
+    )?
+    .destination("org.freedesktop.DBus")?
+    .interface("org.freedesktop.DBus")
+}
+
+/// Decode the reply to a [`get_connection_selinux_security_context`] call.
+///
+/// The context is returned as bytes. If the bus has no SELinux
+/// context for the connection, e.g. because SELinux is disabled,
+/// [`Error::SELinuxSecurityContextUnknown`] is returned. Other error replies are turned into an
+/// [`Error::MethodError`](crate::Error::MethodError).
+pub fn selinux_security_context(reply: &Message) -> crate::Result<Vec<u8>> {
+    reply_body(reply).map_err(|e| {
+        typed_error(
+            e,
+            "org.freedesktop.DBus.Error.SELinuxSecurityContextUnknown",
+            Error::SELinuxSecurityContextUnknown,
+        )
+    })
+}
+
+// Turn an error reply named `name` into the `fdo::Error` made by `make`.
+pub(crate) fn typed_error(e: crate::Error, name: &str, make: fn(String) -> Error) -> crate::Error {
+    match e {
+        crate::Error::MethodError(error_name, description, _) if error_name.as_str() == name => {
+            make(description.unwrap_or_default()).into()
+        }
+        e => e,
+    }
+}
+
+// Deserialize the body of a method return, or turn an error reply into an error.
+fn reply_body<B>(reply: &Message) -> crate::Result<B>
+where
+    B: for<'d> zvariant::DynamicDeserialize<'d>,
+{
+    match reply.message_type() {
+        message::Type::MethodReturn => reply.body().deserialize(),
+        message::Type::Error => Err(reply.clone().into()),
+        _ => Err(crate::Error::InvalidReply),
+    }
+}
+
+/// Errors from <https://gitlab.freedesktop.org/dbus/dbus/-/blob/master/dbus/dbus-protocol.h>
+///
+/// These convert to and from [`crate::Error`], so `?` works across both: an `fdo::Error` becomes
+/// [`crate::Error::FDO`], except for [`Error::ZBus`], which is unwrapped, and the other way
+/// around, [`crate::Error::FDO`] is unwrapped and other errors are wrapped in [`Error::ZBus`].
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::fdo;
+/// fn check(authorized: bool) -> fdo::Result<()> {
+///     if authorized {
+///         Ok(())
+///     } else {
+///         Err(fdo::Error::AccessDenied("not allowed".into()))
+///     }
+/// }
+///
+/// fn caller() -> slimbus::Result<()> {
+///     check(false)?;
+///     Ok(())
+/// }
+///
+/// let e = caller().unwrap_err();
+/// assert_eq!(e, slimbus::Error::FDO(Box::new(fdo::Error::AccessDenied("not allowed".into()))));
+/// assert_eq!(fdo::Error::from(e), fdo::Error::AccessDenied("not allowed".into()));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Error {
+    /// Unknown or fall-through ZBus error.
+    ZBus(zbus::Error),
+
+    /// A generic error; "something went wrong" - see the error message for more.
+    Failed(String),
+
+    /// There was not enough memory to complete an operation.
+    NoMemory(String),
+
+    /// The bus doesn't know how to launch a service to supply the bus name you wanted.
+    ServiceUnknown(String),
+
+    /// The bus name you referenced doesn't exist (i.e. no application owns it).
+    NameHasNoOwner(String),
+
+    /// No reply to a message expecting one, usually means a timeout occurred.
+    NoReply(String),
+
+    /// Something went wrong reading or writing to a socket, for example.
+    IOError(String),
+
+    /// A D-Bus bus address was malformed.
+    BadAddress(String),
+
+    /// Requested operation isn't supported (like ENOSYS on UNIX).
+    NotSupported(String),
+
+    /// Some limited resource is exhausted.
+    LimitsExceeded(String),
+
+    /// Security restrictions don't allow doing what you're trying to do.
+    AccessDenied(String),
+
+    /// Authentication didn't work.
+    AuthFailed(String),
+
+    /// Unable to connect to server (probably caused by ECONNREFUSED on a socket).
+    NoServer(String),
+
+    /// Certain timeout errors, possibly ETIMEDOUT on a socket.
+    /// Note that `TimedOut` is used for message reply timeouts.
+    Timeout(String),
+
+    /// No network access (probably ENETUNREACH on a socket).
+    NoNetwork(String),
+
+    /// Can't bind a socket since its address is in use (i.e. EADDRINUSE).
+    AddressInUse(String),
+
+    /// The connection is disconnected and you're trying to use it.
+    Disconnected(String),
+
+    /// Invalid arguments passed to a method call.
+    InvalidArgs(String),
+
+    /// Missing file.
+    FileNotFound(String),
+
+    /// Existing file and the operation you're using does not silently overwrite.
+    FileExists(String),
+
+    /// Method name you invoked isn't known by the object you invoked it on.
+    UnknownMethod(String),
+
+    /// Object you invoked a method on isn't known.
+    UnknownObject(String),
+
+    /// Interface you invoked a method on isn't known by the object.
+    UnknownInterface(String),
+
+    /// Property you tried to access isn't known by the object.
+    UnknownProperty(String),
+
+    /// Property you tried to set is read-only.
+    PropertyReadOnly(String),
+
+    /// Certain timeout errors, e.g. while starting a service.
+    TimedOut(String),
+
+    /// Tried to remove or modify a match rule that didn't exist.
+    MatchRuleNotFound(String),
+
+    /// The match rule isn't syntactically valid.
+    MatchRuleInvalid(String),
+
+    /// Tried to get a UNIX process ID and it wasn't available.
+    UnixProcessIdUnknown(String),
+
+    /// A type signature is not valid.
+    InvalidSignature(String),
+
+    /// A file contains invalid syntax or is otherwise broken.
+    InvalidFileContent(String),
+
+    /// Asked for SELinux security context and it wasn't available.
+    SELinuxSecurityContextUnknown(String),
+
+    /// Asked for ADT audit data and it wasn't available.
+    AdtAuditDataUnknown(String),
+
+    /// There's already an object with the requested object path.
+    ObjectPathInUse(String),
+
+    /// The message meta data does not match the payload. e.g. expected number of file descriptors
+    /// were not sent over the socket this message was received on.
+    InconsistentMessage(String),
+
+    /// The message is not allowed without performing interactive authorization, but could have
+    /// succeeded if an interactive authorization step was allowed.
+    InteractiveAuthorizationRequired(String),
+
+    /// The connection is not from a container, or the specified container instance does not exist.
+    NotContainer(String),
+}
+
+impl DBusError for Error {
+    fn create_reply(&self, call: &Header<'_>) -> crate::Result<Message> {
+        #[allow(deprecated)]
+        let builder = message::Builder::error(call, self.name())?;
+        match self {
+            Self::ZBus(e) => builder.build(&e.to_string()),
+            _ => match self.description() {
+                Some(desc) => builder.build(&desc),
+                None => builder.build(&()),
+            },
+        }
+    }
+
+    fn name(&self) -> ErrorName<'_> {
+        let name = match self {
+            Self::ZBus(_) => "org.freedesktop.zbus.Error",
+            Self::Failed(_) => "org.freedesktop.DBus.Error.Failed",
+            Self::NoMemory(_) => "org.freedesktop.DBus.Error.NoMemory",
+            Self::ServiceUnknown(_) => "org.freedesktop.DBus.Error.ServiceUnknown",
+            Self::NameHasNoOwner(_) => "org.freedesktop.DBus.Error.NameHasNoOwner",
+            Self::NoReply(_) => "org.freedesktop.DBus.Error.NoReply",
+            Self::IOError(_) => "org.freedesktop.DBus.Error.IOError",
+            Self::BadAddress(_) => "org.freedesktop.DBus.Error.BadAddress",
+            Self::NotSupported(_) => "org.freedesktop.DBus.Error.NotSupported",
+            Self::LimitsExceeded(_) => "org.freedesktop.DBus.Error.LimitsExceeded",
+            Self::AccessDenied(_) => "org.freedesktop.DBus.Error.AccessDenied",
+            Self::AuthFailed(_) => "org.freedesktop.DBus.Error.AuthFailed",
+            Self::NoServer(_) => "org.freedesktop.DBus.Error.NoServer",
+            Self::Timeout(_) => "org.freedesktop.DBus.Error.Timeout",
+            Self::NoNetwork(_) => "org.freedesktop.DBus.Error.NoNetwork",
+            Self::AddressInUse(_) => "org.freedesktop.DBus.Error.AddressInUse",
+            Self::Disconnected(_) => "org.freedesktop.DBus.Error.Disconnected",
+            Self::InvalidArgs(_) => "org.freedesktop.DBus.Error.InvalidArgs",
+            Self::FileNotFound(_) => "org.freedesktop.DBus.Error.FileNotFound",
+            Self::FileExists(_) => "org.freedesktop.DBus.Error.FileExists",
+            Self::UnknownMethod(_) => "org.freedesktop.DBus.Error.UnknownMethod",
+            Self::UnknownObject(_) => "org.freedesktop.DBus.Error.UnknownObject",
+            Self::UnknownInterface(_) => "org.freedesktop.DBus.Error.UnknownInterface",
+            Self::UnknownProperty(_) => "org.freedesktop.DBus.Error.UnknownProperty",
+            Self::PropertyReadOnly(_) => "org.freedesktop.DBus.Error.PropertyReadOnly",
+            Self::TimedOut(_) => "org.freedesktop.DBus.Error.TimedOut",
+            Self::MatchRuleNotFound(_) => "org.freedesktop.DBus.Error.MatchRuleNotFound",
+            Self::MatchRuleInvalid(_) => "org.freedesktop.DBus.Error.MatchRuleInvalid",
+            Self::UnixProcessIdUnknown(_) => "org.freedesktop.DBus.Error.UnixProcessIdUnknown",
+            Self::InvalidSignature(_) => "org.freedesktop.DBus.Error.InvalidSignature",
+            Self::InvalidFileContent(_) => "org.freedesktop.DBus.Error.InvalidFileContent",
+            Self::SELinuxSecurityContextUnknown(_) => {
+                "org.freedesktop.DBus.Error.SELinuxSecurityContextUnknown"
+            }
+            Self::AdtAuditDataUnknown(_) => "org.freedesktop.DBus.Error.AdtAuditDataUnknown",
+            Self::ObjectPathInUse(_) => "org.freedesktop.DBus.Error.ObjectPathInUse",
+            Self::InconsistentMessage(_) => "org.freedesktop.DBus.Error.InconsistentMessage",
+            Self::InteractiveAuthorizationRequired(_) => {
+                "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired"
+            }
+            Self::NotContainer(_) => "org.freedesktop.DBus.Error.NotContainer",
+        };
+
+        ErrorName::from_static_str_unchecked(name)
+    }
+
+    fn description(&self) -> Option<&str> {
+        match self {
+            Self::ZBus(_) => None,
+            Self::Failed(desc)
+            | Self::NoMemory(desc)
+            | Self::ServiceUnknown(desc)
+            | Self::NameHasNoOwner(desc)
+            | Self::NoReply(desc)
+            | Self::IOError(desc)
+            | Self::BadAddress(desc)
+            | Self::NotSupported(desc)
+            | Self::LimitsExceeded(desc)
+            | Self::AccessDenied(desc)
+            | Self::AuthFailed(desc)
+            | Self::NoServer(desc)
+            | Self::Timeout(desc)
+            | Self::NoNetwork(desc)
+            | Self::AddressInUse(desc)
+            | Self::Disconnected(desc)
+            | Self::InvalidArgs(desc)
+            | Self::FileNotFound(desc)
+            | Self::FileExists(desc)
+            | Self::UnknownMethod(desc)
+            | Self::UnknownObject(desc)
+            | Self::UnknownInterface(desc)
+            | Self::UnknownProperty(desc)
+            | Self::PropertyReadOnly(desc)
+            | Self::TimedOut(desc)
+            | Self::MatchRuleNotFound(desc)
+            | Self::MatchRuleInvalid(desc)
+            | Self::UnixProcessIdUnknown(desc)
+            | Self::InvalidSignature(desc)
+            | Self::InvalidFileContent(desc)
+            | Self::SELinuxSecurityContextUnknown(desc)
+            | Self::AdtAuditDataUnknown(desc)
+            | Self::ObjectPathInUse(desc)
+            | Self::InconsistentMessage(desc)
+            | Self::InteractiveAuthorizationRequired(desc)
+            | Self::NotContainer(desc) => Some(desc),
+        }
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(value: zbus::Error) -> Self {
+        match value {
+            zbus::Error::FDO(e) => *e,
+            e => Error::ZBus(e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Alias for a `Result` with the error type [`zbus::fdo::Error`].
+///
+/// [`zbus::fdo::Error`]: enum.Error.html
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,5 +1,5 @@
 mod dbus_error;
-use std::os::fd::RawFd;
+use std::{os::fd::RawFd, time::Duration};
 
 pub use dbus_error::*;
 
@@ -22,6 +22,14 @@ pub mod connection;
 pub use connection as conn;
 pub use connection::{handshake::AuthMechanism, Connection, SocketReader};
 
+mod match_rule;
+pub use match_rule::MatchRule;
+
+pub mod names;
+
+pub mod object_server;
+pub use object_server::ObjectServer;
+
 mod utils;
 pub use utils::*;
 
@@ -35,7 +43,6 @@ pub use connection::Socket;
 // Required for the macros to function within this crate.
 extern crate self as zbus;
 
-pub use zbus_names as names;
 pub use zvariant;
 
 use nix::libc;
@@ -56,16 +63,13 @@ pub fn set_blocking(fd: RawFd, blocking: bool) {
     let _ = unsafe { libc::fcntl(fd, libc::F_SETFL, flags) != -1 };
 }
 
+/// Wait up to `timeout` milliseconds for `fd` to become readable, or indefinitely if `timeout` is
+/// negative.
+///
+/// A wait interrupted by a signal is retried for the rest of the timeout, rather than returning
+/// early.
 pub fn poll(fd: RawFd, timeout: i32) {
-    let fd = libc::pollfd {
-        fd,
-        events: libc::POLLIN,
-        revents: 0,
-    };
+    let timeout = u64::try_from(timeout).map_or(Duration::MAX, Duration::from_millis);
 
-    let mut fds = [fd];
-
-    unsafe {
-        libc::poll(fds.as_mut_ptr(), fds.len() as u64, timeout);
-    }
+    let _ = utils::wait_readable(fd, timeout);
 }
@@ -0,0 +1,118 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::Serialize;
+use zbus_names::{BusName, InterfaceName};
+use zvariant::{ObjectPath, Type};
+
+use crate::Error;
+
+/// A bus [match rule], selecting the messages the bus should route to a connection.
+///
+/// It serializes as its string form, so it can be passed as is as the body of an
+/// `org.freedesktop.DBus.AddMatch` or `RemoveMatch` call.
+///
+/// [match rule]: https://dbus.freedesktop.org/doc/dbus-specification.html#message-bus-routing-match-rules
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Type, Serialize)]
+pub struct MatchRule(String);
+
+impl MatchRule {
+    /// A rule matching the `org.freedesktop.DBus.Properties.PropertiesChanged` signals of the
+    /// properties of `interface` on the object at `path`.
+    ///
+    /// Decode the matching signals with [`fdo::PropertiesChanged`](crate::fdo::PropertiesChanged).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::MatchRule;
+    /// let rule = MatchRule::properties_changed("org.example.Player", "/org/example/player").unwrap();
+    ///
+    /// assert_eq!(
+    ///     rule.as_str(),
+    ///     "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',\
+    ///      path='/org/example/player',arg0='org.example.Player'",
+    /// );
+    /// assert!(MatchRule::properties_changed("org.example.Player", "no/path").is_err());
+    /// ```
+    pub fn properties_changed<'i, 'p, I, P>(interface: I, path: P) -> Result<Self, Error>
+    where
+        I: TryInto<InterfaceName<'i>>,
+        P: TryInto<ObjectPath<'p>>,
+        I::Error: Into<Error>,
+        P::Error: Into<Error>,
+    {
+        let interface = interface.try_into().map_err(Into::into)?;
+        let path = path.try_into().map_err(Into::into)?;
+
+        Ok(Self(format!(
+            "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',\
+             path={},arg0={}",
+            quote(&path),
+            quote(&interface),
+        )))
+    }
+
+    /// A rule matching the `org.freedesktop.DBus.NameOwnerChanged` signals of the bus about `name`.
+    ///
+    /// Decode the matching signals with [`fdo::DBusSignal`](crate::fdo::DBusSignal).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::MatchRule;
+    /// let rule = MatchRule::name_owner_changed("org.example.Service").unwrap();
+    ///
+    /// assert_eq!(
+    ///     rule.as_str(),
+    ///     "type='signal',sender='org.freedesktop.DBus',interface='org.freedesktop.DBus',\
+    ///      member='NameOwnerChanged',arg0='org.example.Service'",
+    /// );
+    /// assert!(MatchRule::name_owner_changed("no name").is_err());
+    /// ```
+    pub fn name_owner_changed<'n, N>(name: N) -> Result<Self, Error>
+    where
+        N: TryInto<BusName<'n>>,
+        N::Error: Into<Error>,
+    {
+        let name = name.try_into().map_err(Into::into)?;
+
+        Ok(Self(format!(
+            "type='signal',sender='org.freedesktop.DBus',interface='org.freedesktop.DBus',\
+             member='NameOwnerChanged',arg0={}",
+            quote(&name),
+        )))
+    }
+
+    /// The string form of the rule.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for MatchRule {
+    /// Use `rule` as is. It's validated by the bus when added, see
+    /// [`Connection::add_match`](crate::Connection::add_match).
+    fn from(rule: String) -> Self {
+        Self(rule)
+    }
+}
+
+impl From<&str> for MatchRule {
+    /// Use `rule` as is. It's validated by the bus when added, see
+    /// [`Connection::add_match`](crate::Connection::add_match).
+    fn from(rule: &str) -> Self {
+        Self(rule.to_owned())
+    }
+}
+
+impl Display for MatchRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// Quote a value of a match rule. Within quotes, nothing is escaped, so an apostrophe is written by
+// closing the quotes, escaping it with a backslash, and opening them again.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
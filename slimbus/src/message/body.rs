@@ -1,9 +1,13 @@
 use zvariant::{
     serialized::{self, Data},
-    Signature, Type,
+    Endian, MaxDepthExceeded, OwnedStructure, OwnedValue, Signature, Type,
 };
 
-use crate::{Error, Message, Result};
+use crate::{
+    fdo,
+    utils::{body_signature, padding_for_n_bytes, signature::complete_types},
+    Error, Message, Result,
+};
 
 /// The body of a message.
 ///
@@ -20,6 +24,27 @@ impl Body {
     }
 
     /// Deserialize the body using the contained signature.
+    ///
+    /// If `B` can not be deserialized from the signature of the body, an
+    /// [`Error::SignatureMismatch`] is returned. If the body refers to a file descriptor the
+    /// message doesn't carry, [`fdo::Error::InconsistentMessage`] is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::os::fd::OwnedFd;
+    /// # use slimbus::{fdo, zvariant, Error, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let fd = OwnedFd::from(std::fs::File::open("/dev/null")?);
+    /// // The body claims FD index 2, but only one FD is attached.
+    /// let msg = Message::method("/", "Open")?.build_with_fds(&2i32, "h", vec![fd.into()])?;
+    ///
+    /// assert!(matches!(
+    ///     msg.body().deserialize::<zvariant::OwnedFd>(),
+    ///     Err(Error::FDO(e)) if matches!(*e, fdo::Error::InconsistentMessage(_))
+    /// ));
+    /// # Ok(()) })().unwrap()
+    /// ```
     pub fn deserialize<'s, B>(&'s self) -> Result<B>
     where
         B: zvariant::DynamicDeserialize<'s>,
@@ -30,16 +55,197 @@ impl Body {
 
         self.data
             .deserialize_for_dynamic_signature(body_sig)
-            .map_err(Error::from)
+            .map_err(deserialize_error)
             .map(|b| b.0)
     }
 
+    /// Deserialize only the field at `index` of the body.
+    ///
+    /// The preceding fields are skipped according to the signature of the body, without being
+    /// deserialized, and the following ones are ignored. This is cheaper than deserializing the
+    /// whole body when only one field is needed, e.g. the response code of a reply also carrying a
+    /// large dictionary.
+    ///
+    /// If `T` can not be deserialized from the signature of the field, an
+    /// [`Error::SignatureMismatch`] is returned. If the body has no field at `index`,
+    /// [`zvariant::Error::OutOfBounds`] is returned. Preceding fields nested deeper than the
+    /// specification allows result in [`zvariant::Error::MaxDepthExceeded`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use slimbus::{zvariant::{self, Value}, Error, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let results = HashMap::from([("uris", Value::from(vec!["file:///tmp/a"]))]);
+    /// let msg = Message::method("/", "Response")?.build(&(0u32, results, "done"))?;
+    /// let body = msg.body();
+    ///
+    /// assert_eq!(body.deserialize_field::<u32>(0)?, 0);
+    /// assert_eq!(body.deserialize_field::<String>(2)?, "done");
+    /// assert!(body.deserialize_field::<u32>(3).is_err());
+    ///
+    /// // A variant holding a variant holding a variant... 100 levels deep.
+    /// let mut nested = [1, b'v', 0].repeat(100);
+    /// nested.extend([1, b'u', 0, 0, 7, 0, 0, 0, 7, 0, 0, 0]);
+    /// let msg = unsafe { Message::method("/", "Nested")?.build_raw_body(&nested, "vu", vec![]) }?;
+    /// assert!(matches!(
+    ///     msg.body().deserialize_field::<u32>(1),
+    ///     Err(Error::Variant(zvariant::Error::MaxDepthExceeded(_)))
+    /// ));
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn deserialize_field<T>(&self, index: usize) -> Result<T>
+    where
+        T: for<'d> zvariant::DynamicDeserialize<'d>,
+    {
+        let body_sig = self
+            .signature()
+            .unwrap_or_else(|| Signature::from_static_str_unchecked(""));
+        let endian = self.data.context().endian();
+
+        let mut types = complete_types(&body_sig);
+        let mut pos = 0;
+        for ty in types.by_ref().take(index) {
+            pos = skip_value(&self.data, endian, pos, &ty, Depth::default())?;
+        }
+        let field_sig = types
+            .next()
+            .ok_or(Error::Variant(zvariant::Error::OutOfBounds))?;
+
+        self.data
+            .slice(pos..)
+            .deserialize_for_dynamic_signature(field_sig)
+            .map_err(deserialize_error)
+            .map(|f| f.0)
+    }
+
+    /// Deserialize the leading fields of the body into `T`, ignoring the following ones.
+    ///
+    /// Along with the value, the number of body bytes it was deserialized from is returned, so the
+    /// rest of the body can be processed separately, e.g. a trailing blob from [`Body::data`] of a
+    /// protocol layered on top of D-Bus. Note that the next field starts after the padding to its
+    /// alignment, if any.
+    ///
+    /// If the complete types of the signature of `T` aren't the leading complete types of the
+    /// signature of the body, an [`Error::SignatureMismatch`] is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{zvariant::Endian, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let blob = vec![0xde_u8, 0xad, 0xbe, 0xef];
+    /// let msg = Message::method("/", "Frame")?
+    ///     .endian(Endian::Little)
+    ///     .build(&(7u32, "frame", &blob))?;
+    /// let body = msg.body();
+    ///
+    /// let ((version, kind), consumed) = body.deserialize_prefix::<(u32, String)>()?;
+    /// assert_eq!((version, kind.as_str()), (7, "frame"));
+    ///
+    /// // The tail is the `ay` array, aligned to 4 bytes: its length, then its bytes.
+    /// let tail = &body.data()[consumed.next_multiple_of(4)..];
+    /// assert_eq!(tail[..4], 4u32.to_le_bytes());
+    /// assert_eq!(tail[4..], blob);
+    ///
+    /// assert!(body.deserialize_prefix::<(u32, u8)>().is_err());
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn deserialize_prefix<'d, 'm: 'd, T>(&'m self) -> Result<(T, usize)>
+    where
+        T: serde::de::Deserialize<'d> + Type,
+    {
+        let expected = T::signature();
+        let prefix = body_signature(expected.clone());
+        let actual = self
+            .signature()
+            .unwrap_or_else(|| Signature::from_static_str_unchecked(""));
+        let mut actual_types = complete_types(&actual);
+        if !complete_types(&prefix).all(|ty| actual_types.next().is_some_and(|a| a == ty)) {
+            return Err(Error::SignatureMismatch {
+                expected: prefix.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+
+        // The fields of the body are encoded the same as a structure of them, which starts at the
+        // 8-byte aligned start of the body.
+        self.data
+            .deserialize_for_signature(expected)
+            .map_err(deserialize_error)
+    }
+
+    /// Deserialize each field of the body into an [`OwnedValue`], according to the signature.
+    ///
+    /// This is useful to show or process the arguments of an arbitrary message, without knowing
+    /// their types ahead of time. An empty body gives no values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{zvariant::Value, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/", "SetColor")?.build(&("sky", (0u8, 128u8, 255u8)))?;
+    /// let values = msg.body().to_owned_value_vec()?;
+    ///
+    /// assert_eq!(values.len(), 2);
+    /// assert_eq!(*values[0], Value::from("sky"));
+    /// assert_eq!(values[1].value_signature(), "(yyy)");
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn to_owned_value_vec(&self) -> Result<Vec<OwnedValue>> {
+        let Some(body_sig) = self.signature().filter(|s| !s.is_empty()) else {
+            return Ok(Vec::new());
+        };
+
+        // The fields of the body are encoded the same as a structure of them.
+        let struct_sig = Signature::from_string_unchecked(format!("({body_sig})"));
+        let (OwnedStructure(structure), _) = self
+            .data
+            .deserialize_for_dynamic_signature::<_, OwnedStructure>(struct_sig)
+            .map_err(deserialize_error)?;
+
+        structure
+            .into_fields()
+            .into_iter()
+            .map(|field| OwnedValue::try_from(field).map_err(Error::from))
+            .collect()
+    }
+
     /// Deserialize the body (without checking signature matching).
     pub fn deserialize_unchecked<'d, 'm: 'd, B>(&'m self) -> Result<B>
     where
         B: serde::de::Deserialize<'d> + Type,
     {
-        self.data.deserialize().map_err(Error::from).map(|b| b.0)
+        self.data
+            .deserialize()
+            .map_err(deserialize_error)
+            .map(|b| b.0)
+    }
+
+    /// Deserialize the body, checking the signature of `B` against the one of the body first.
+    ///
+    /// Unlike [`Body::deserialize_unchecked`], a type not matching the body results in an
+    /// [`Error::SignatureMismatch`] naming both signatures, rather than an error from somewhere
+    /// deep in the deserializer.
+    pub fn deserialize_checked<'d, 'm: 'd, B>(&'m self) -> Result<B>
+    where
+        B: serde::de::Deserialize<'d> + Type,
+    {
+        let expected = B::signature();
+        let expected = body_signature(expected);
+        let actual = self
+            .signature()
+            .unwrap_or_else(|| Signature::from_static_str_unchecked(""));
+        if expected != actual {
+            return Err(Error::SignatureMismatch {
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+
+        self.deserialize_unchecked()
     }
 
     /// The signature of the body.
@@ -72,3 +278,136 @@ impl Body {
         &self.msg
     }
 }
+
+// Turn a deserialization error from zvariant into our own, more descriptive one where possible.
+pub(super) fn deserialize_error(e: zvariant::Error) -> Error {
+    match e {
+        zvariant::Error::SignatureMismatch(actual, expected) => Error::SignatureMismatch {
+            expected: expected.trim_matches('`').to_string(),
+            actual: actual.to_string(),
+        },
+        // The body refers to an FD the message doesn't carry.
+        zvariant::Error::UnknownFd => fdo::Error::InconsistentMessage(
+            "file descriptor index out of range of the message's file descriptors".to_owned(),
+        )
+        .into(),
+        e => Error::from(e),
+    }
+}
+
+// The nesting of the containers around a value, limited as in the specification: 32 levels of
+// structures and 64 levels of containers in total. Arrays are skipped as a whole, so only
+// structures and variants are nested into.
+#[derive(Clone, Copy, Debug, Default)]
+struct Depth {
+    structure: u8,
+    total: u8,
+}
+
+impl Depth {
+    fn structure(self) -> Result<Self> {
+        if self.structure >= 32 {
+            return Err(Error::Variant(zvariant::Error::MaxDepthExceeded(
+                MaxDepthExceeded::Structure,
+            )));
+        }
+
+        Self {
+            structure: self.structure + 1,
+            ..self
+        }
+        .container()
+    }
+
+    fn container(self) -> Result<Self> {
+        if self.total >= 64 {
+            return Err(Error::Variant(zvariant::Error::MaxDepthExceeded(
+                MaxDepthExceeded::Container,
+            )));
+        }
+
+        Ok(Self {
+            total: self.total + 1,
+            ..self
+        })
+    }
+}
+
+// The position after the encoded value of the complete type `signature` starting at `pos`, nested
+// in containers as deep as `depth`.
+//
+// Positions are relative to the start of the body, which is 8-byte aligned in the message.
+fn skip_value(
+    bytes: &[u8],
+    endian: Endian,
+    pos: usize,
+    signature: &Signature<'_>,
+    depth: Depth,
+) -> Result<usize> {
+    let out_of_bounds = || Error::Variant(zvariant::Error::OutOfBounds);
+    let end = |pos: usize, len: usize| {
+        pos.checked_add(len)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(out_of_bounds)
+    };
+    let fixed = |pos: usize, size: usize| end(pos + padding_for_n_bytes(pos, size), size);
+    let read_u32 = |pos: usize| {
+        let pos = pos + padding_for_n_bytes(pos, 4);
+        let len = bytes.get(pos..end(pos, 4)?).ok_or_else(out_of_bounds)?;
+
+        Ok::<_, Error>((pos + 4, endian.read_u32(len) as usize))
+    };
+
+    match signature.as_bytes()[0] {
+        b'y' => fixed(pos, 1),
+        b'n' | b'q' => fixed(pos, 2),
+        b'b' | b'i' | b'u' | b'h' => fixed(pos, 4),
+        b'x' | b't' | b'd' => fixed(pos, 8),
+        b's' | b'o' => {
+            let (pos, len) = read_u32(pos)?;
+
+            // The string is followed by a nul byte.
+            end(pos, len + 1)
+        }
+        b'g' => {
+            let len = *bytes.get(pos).ok_or_else(out_of_bounds)? as usize;
+
+            end(pos + 1, len + 1)
+        }
+        b'v' => {
+            let len = *bytes.get(pos).ok_or_else(out_of_bounds)? as usize;
+            let value_sig = bytes
+                .get(pos + 1..end(pos + 1, len)?)
+                .ok_or_else(out_of_bounds)?;
+            let value_sig = Signature::try_from(value_sig)?;
+            if complete_types(&value_sig).count() != 1 {
+                return Err(Error::Variant(zvariant::Error::IncorrectType));
+            }
+
+            skip_value(bytes, endian, pos + len + 2, &value_sig, depth.container()?)
+        }
+        b'a' => {
+            let (pos, len) = read_u32(pos)?;
+            // The elements are aligned even if there are none.
+            let alignment = match signature.as_bytes()[1] {
+                b'y' | b'g' | b'v' => 1,
+                b'n' | b'q' => 2,
+                b'x' | b't' | b'd' | b'(' | b'{' => 8,
+                _ => 4,
+            };
+
+            end(pos + padding_for_n_bytes(pos, alignment), len)
+        }
+        b'(' | b'{' => {
+            let depth = depth.structure()?;
+            let mut pos = end(pos + padding_for_n_bytes(pos, 8), 0)?;
+            let fields = signature.slice(1..signature.len() - 1);
+            for field in complete_types(&fields) {
+                pos = skip_value(bytes, endian, pos, &field, depth)?;
+            }
+
+            Ok(pos)
+        }
+        _ => Err(Error::Variant(zvariant::Error::IncorrectType)),
+    }
+}
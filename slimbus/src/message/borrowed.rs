@@ -0,0 +1,111 @@
+use std::os::fd::AsFd;
+
+use zvariant::{serialized::Data, Signature};
+
+use crate::{
+    message::{
+        body::deserialize_error, fields::QuickFields, Header, PrimaryHeader, Sequence, Type,
+    },
+    Message, Result,
+};
+
+/// A message borrowing its bytes from the buffer it was received into.
+///
+/// This is returned by [`SocketReader::read_borrowed`], which reuses the same buffer for every
+/// message, rather than allocating a new one for each [`Message`]. It is thus only valid until the
+/// next read, which suits handlers that fully process each message before reading the next one.
+/// Use [`MessageRef::to_message`] to keep a message around.
+///
+/// [`SocketReader::read_borrowed`]: crate::SocketReader::read_borrowed
+#[derive(Debug)]
+pub struct MessageRef<'m> {
+    primary_header: PrimaryHeader,
+    quick_fields: QuickFields,
+    bytes: Data<'m, 'static>,
+    body: Data<'m, 'static>,
+    recv_seq: Sequence,
+}
+
+impl<'m> MessageRef<'m> {
+    pub(crate) fn new(bytes: Data<'m, 'static>, recv_seq: Sequence) -> Result<Self> {
+        let (primary_header, quick_fields, body_offset) = super::parse_parts(&bytes)?;
+        let body = bytes.slice(body_offset..);
+
+        Ok(Self {
+            primary_header,
+            quick_fields,
+            bytes,
+            body,
+            recv_seq,
+        })
+    }
+
+    /// The primary header of the message.
+    pub fn primary_header(&self) -> &PrimaryHeader {
+        &self.primary_header
+    }
+
+    /// The message header.
+    ///
+    /// Like [`Message::header`], this doesn't deserialize the header, but reassembles it from the
+    /// fields located when the message was received.
+    pub fn header(&self) -> Header<'_> {
+        self.quick_fields
+            .header(&self.primary_header, self.bytes.bytes())
+    }
+
+    /// The message type.
+    pub fn message_type(&self) -> Type {
+        self.primary_header.msg_type()
+    }
+
+    /// Deserialize the body using the contained signature.
+    ///
+    /// See [`Body::deserialize`](crate::message::Body::deserialize).
+    pub fn deserialize_body<'s, B>(&'s self) -> Result<B>
+    where
+        B: zvariant::DynamicDeserialize<'s>,
+    {
+        let body_sig = self
+            .quick_fields
+            .header(&self.primary_header, self.bytes.bytes())
+            .signature()
+            .cloned()
+            .unwrap_or_else(|| Signature::from_static_str_unchecked(""));
+
+        self.body
+            .deserialize_for_dynamic_signature(body_sig)
+            .map_err(deserialize_error)
+            .map(|b| b.0)
+    }
+
+    /// The encoded message, borrowing the receive buffer.
+    pub fn data(&self) -> &Data<'m, 'static> {
+        &self.bytes
+    }
+
+    /// The encoded body, borrowing the receive buffer.
+    pub fn body_data(&self) -> &Data<'m, 'static> {
+        &self.body
+    }
+
+    /// Get the receive ordering of the message.
+    ///
+    /// See [`Message::recv_position`].
+    pub fn recv_position(&self) -> Sequence {
+        self.recv_seq
+    }
+
+    /// Copy the message into an owned [`Message`], duplicating the file descriptors it carries.
+    pub fn to_message(&self) -> Result<Message> {
+        let fds = self
+            .bytes
+            .fds()
+            .iter()
+            .map(|fd| fd.as_fd().try_clone_to_owned())
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let bytes = Data::new_fds(self.bytes.bytes().to_vec(), self.bytes.context(), fds);
+
+        Message::from_raw_parts(bytes, self.recv_seq, None)
+    }
+}
@@ -1,6 +1,8 @@
 use std::{
     io::{Cursor, Write},
-    sync::Arc,
+    num::NonZeroU32,
+    os::fd::AsFd,
+    sync::{Arc, OnceLock},
 };
 use zvariant::OwnedFd;
 
@@ -9,13 +11,23 @@ use zbus_names::{BusName, ErrorName, InterfaceName, MemberName, UniqueName};
 use zvariant::{serialized, Endian};
 
 use crate::{
-    message::{Field, FieldCode, Fields, Flags, Header, Message, PrimaryHeader, Sequence, Type},
-    utils::padding_for_8_bytes,
-    zvariant::{serialized::Context, DynamicType, ObjectPath, Signature},
+    message::{
+        Field, FieldCode, Fields, Flags, Header, Message, PrimaryHeader, ReplyContext, Sequence,
+        Type,
+    },
+    utils::{body_signature, signature::count_fds_in_signature},
+    zvariant::{
+        serialized::Context, DynamicType, ObjectPath, OwnedValue, Signature, Value,
+        STRUCT_SIG_START_CHAR,
+    },
     EndianSig, Error, Result,
 };
 
-use crate::message::{fields::QuickFields, header::MAX_MESSAGE_SIZE};
+use crate::message::{
+    field::has_fds,
+    fields::QuickFields,
+    header::{message_len, next_serial_num, DEFAULT_MAX_FDS, MAX_MESSAGE_SIZE},
+};
 
 type BuildGenericResult = Vec<OwnedFd>;
 
@@ -26,9 +38,71 @@ macro_rules! dbus_context {
 }
 
 /// A builder for [`Message`]
-#[derive(Debug, Clone)]
+///
+/// A builder can be cloned to build many messages sharing some of their header fields, e.g. calls
+/// to different methods of the same interface. Each clone is independent and gets its own serial
+/// number.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::Message;
+/// # (|| -> slimbus::Result<()> {
+/// let base = Message::method("/org/example/Object", "Unset")?
+///     .destination("org.example.Service")?
+///     .interface("org.example.Interface")?;
+///
+/// let get = base.clone().member("Get")?.build(&"key")?;
+/// let set = base.clone().member("Set")?.build(&("key", 42u32))?;
+/// let unset = base.build(&())?;
+///
+/// assert_eq!(get.header().member().unwrap(), "Get");
+/// assert_eq!(set.header().member().unwrap(), "Set");
+/// assert_eq!(unset.header().member().unwrap(), "Unset");
+/// assert_eq!(set.header().interface().unwrap(), "org.example.Interface");
+/// assert_eq!(unset.body().signature(), None);
+///
+/// let serials = [&get, &set, &unset].map(|m| m.primary_header().serial_num());
+/// assert!(serials[0] != serials[1] && serials[1] != serials[2] && serials[0] != serials[2]);
+/// # Ok(()) })().unwrap()
+/// ```
+#[derive(Debug)]
 pub struct Builder<'a> {
     header: Header<'a>,
+    max_fds: usize,
+}
+
+/// A builder for a [`Message`] whose body is assembled from several values
+///
+/// This is created by [`Builder::append`], see there for details. Cloning it clones the values
+/// appended so far, as cloning a [`Builder`] does with the header fields.
+#[derive(Clone, Debug)]
+pub struct AppendingBuilder<'a> {
+    builder: Builder<'a>,
+    body: AppendedBody,
+}
+
+// The body assembled by `AppendingBuilder::append`.
+//
+// The FDs are shared between clones of the builder, and duplicated when building.
+#[derive(Clone, Debug, Default)]
+struct AppendedBody {
+    bytes: Vec<u8>,
+    signature: String,
+    fds: Vec<Arc<OwnedFd>>,
+}
+
+impl Clone for Builder<'_> {
+    fn clone(&self) -> Self {
+        let mut header = self.header.clone();
+        // Messages built from the clone must not share the serial number of the original.
+        header.primary_mut().set_serial_num(next_serial_num());
+
+        Self {
+            header,
+            max_fds: self.max_fds,
+        }
+    }
 }
 
 impl<'a> Builder<'a> {
@@ -36,7 +110,10 @@ impl<'a> Builder<'a> {
         let primary = PrimaryHeader::new(msg_type, 0);
         let fields = Fields::new();
         let header = Header::new(primary, fields);
-        Self { header }
+        Self {
+            header,
+            max_fds: DEFAULT_MAX_FDS,
+        }
     }
 
     /// Create a message of type [`Type::MethodCall`].
@@ -84,6 +161,42 @@ impl<'a> Builder<'a> {
         Self::new(Type::Error).error_name(name)?.reply_to(reply_to)
     }
 
+    pub(super) fn method_return_for_context(context: &ReplyContext) -> Result<Self> {
+        Self::new(Type::MethodReturn).reply_fields(
+            context.serial(),
+            context.endian(),
+            context.sender(),
+        )
+    }
+
+    pub(super) fn error_for_context<'e: 'a, E>(context: &ReplyContext, name: E) -> Result<Self>
+    where
+        E: TryInto<ErrorName<'e>>,
+        E::Error: Into<Error>,
+    {
+        Self::new(Type::Error).error_name(name)?.reply_fields(
+            context.serial(),
+            context.endian(),
+            context.sender(),
+        )
+    }
+
+    // Same as `method_return` but avoids building the header of `call`.
+    pub(super) fn method_return_for(call: &Message) -> Result<Self> {
+        Self::new(Type::MethodReturn).reply_to_message(call)
+    }
+
+    // Same as `error` but avoids building the header of `call`.
+    pub(super) fn error_for<'e: 'a, E>(call: &Message, name: E) -> Result<Self>
+    where
+        E: TryInto<ErrorName<'e>>,
+        E::Error: Into<Error>,
+    {
+        Self::new(Type::Error)
+            .error_name(name)?
+            .reply_to_message(call)
+    }
+
     /// Add flags to the message.
     ///
     /// See [`Flags`] documentation for the meaning of the flags.
@@ -171,12 +284,91 @@ impl<'a> Builder<'a> {
         Ok(self)
     }
 
-    fn reply_to(mut self, reply_to: &Header<'_>) -> Result<Self> {
-        let serial = reply_to.primary().serial_num();
+    /// Set a header field by its raw `code`, with `value` serialized as a variant.
+    ///
+    /// This is an escape hatch for fields that this crate has no typed setter for, e.g. ones
+    /// defined by a newer version of the specification. Any existing field with the same code is
+    /// replaced.
+    ///
+    /// Returns [`Error::InvalidField`] if `code` is `0` (invalid per the specification) or a code
+    /// known to this crate, for which the typed setters should be used instead, or if `value`
+    /// contains file descriptors. Likewise, a received message with an unknown field carrying file
+    /// descriptors is rejected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{
+    /// #     zvariant::{serialized::{Context, Data}, Endian, Fd, OwnedValue, Value},
+    /// #     Error, Message,
+    /// # };
+    /// # use std::os::fd::AsFd;
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/", "Ping")?
+    ///     .endian(Endian::Little)
+    ///     .raw_field(200, OwnedValue::from(7u32))?
+    ///     .build(&())?;
+    ///
+    /// let file = std::fs::File::open("/dev/null")?;
+    /// let fd = Value::Value(Box::new(Value::from(Fd::from(file.as_fd()))));
+    /// let builder = Message::method("/", "Ping")?.raw_field(201, fd.try_to_owned()?);
+    /// assert_eq!(builder.unwrap_err(), Error::InvalidField);
+    ///
+    /// // Turn the `u` value of the unknown field into an FD index, which is laid out the same.
+    /// let mut bytes = msg.data().bytes().to_vec();
+    /// let sig = bytes.windows(3).position(|w| w == [200, 1, b'u']).unwrap() + 2;
+    /// bytes[sig] = b'h';
+    /// bytes[sig + 2..sig + 6].copy_from_slice(&0u32.to_le_bytes());
+    /// let fds = vec![file.as_fd().try_clone_to_owned()?];
+    /// let data = Data::new_fds(bytes, Context::new_dbus(Endian::Little, 0), fds);
+    /// assert!(unsafe { Message::from_bytes(data) }.is_err());
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn raw_field(mut self, code: u8, value: OwnedValue) -> Result<Self> {
+        if code == 0 || FieldCode::try_from(code).is_ok() {
+            return Err(Error::InvalidField);
+        }
+        let value = Value::from(value);
+        if has_fds(&value) {
+            return Err(Error::InvalidField);
+        }
+        self.header
+            .fields_mut()
+            .replace(Field::Unknown(code, value));
+        Ok(self)
+    }
+
+    fn reply_to(self, reply_to: &Header<'_>) -> Result<Self> {
+        let primary = reply_to.primary();
+
+        self.reply_fields(
+            primary.serial_num(),
+            primary.endian_sig(),
+            reply_to.sender(),
+        )
+    }
+
+    // Same as `reply_to` but reads the fields from the quick fields of `call`.
+    fn reply_to_message(self, call: &Message) -> Result<Self> {
+        let primary = call.primary_header();
+        let sender = call.inner.quick_fields.sender(call);
+
+        self.reply_fields(primary.serial_num(), primary.endian_sig(), sender.as_ref())
+    }
+
+    // Set the fields of a reply to the call with the given serial, endianness and sender.
+    //
+    // The flags of the call are not copied, a reply never carries e.g. `NoReplyExpected`.
+    fn reply_fields(
+        mut self,
+        serial: NonZeroU32,
+        endian: EndianSig,
+        sender: Option<&UniqueName<'_>>,
+    ) -> Result<Self> {
         self.header.fields_mut().replace(Field::ReplySerial(serial));
-        self = self.endian(reply_to.primary().endian_sig().into());
+        self = self.endian(endian.into());
 
-        if let Some(sender) = reply_to.sender() {
+        if let Some(sender) = sender {
             self.destination(sender.to_owned())
         } else {
             Ok(self)
@@ -185,7 +377,24 @@ impl<'a> Builder<'a> {
 
     /// Set the endianness of the message.
     ///
-    /// The default endianness is native.
+    /// The default endianness is native. Both the header and the body are encoded in the given
+    /// endianness.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{message::{EndianSig, Message}, zvariant::Endian};
+    /// # (|| -> slimbus::Result<()> {
+    /// let message = Message::method("/", "ping")?
+    ///     .endian(Endian::Big)
+    ///     .build(&(1u32, 2u16))?;
+    /// assert_eq!(message.primary_header().endian_sig(), EndianSig::Big);
+    /// assert_eq!(message.body().data().bytes(), [0, 0, 0, 1, 0, 2]);
+    ///
+    /// let message = unsafe { Message::from_bytes(message.data().clone()) }?;
+    /// assert_eq!(message.body().deserialize::<(u32, u16)>()?, (1, 2));
+    /// # Ok(()) })().unwrap()
+    /// ```
     pub fn endian(mut self, endian: Endian) -> Self {
         let sig = EndianSig::from(endian);
         self.header.primary_mut().set_endian_sig(sig);
@@ -193,13 +402,96 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Set the maximum number of file descriptors the message may carry.
+    ///
+    /// Building a message with more FDs fails with [`Error::ExcessData`], as for a message over the
+    /// maximum size, rather than having the message rejected by the peer. The default is 253, the
+    /// limit of the Linux kernel and hence of the D-Bus daemon.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::os::fd::OwnedFd;
+    /// # use slimbus::{Error, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let fd = OwnedFd::from(std::fs::File::open("/dev/null")?);
+    /// let res = Message::method("/", "Close")?
+    ///     .max_fds(0)
+    ///     .build_with_fds(&(0i32,), "h", vec![fd.into()]);
+    ///
+    /// assert_eq!(res.unwrap_err(), Error::ExcessData);
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn max_fds(mut self, max_fds: usize) -> Self {
+        self.max_fds = max_fds;
+
+        self
+    }
+
+    /// Append `value` to the body of the message, returning a builder for the rest of the body.
+    ///
+    /// This allows assembling the body from several values, e.g. coming from different code
+    /// paths, rather than a single one. Further values are appended with
+    /// [`AppendingBuilder::append`], and the message is built with [`AppendingBuilder::build`].
+    /// The values are serialized one after the other, aligned as in a single body, and the body
+    /// signature is the concatenation of their signatures. Unlike with [`Builder::build`], a tuple
+    /// is appended as a STRUCT, unless it is the only value.
+    ///
+    /// Only one of the appended values may contain file descriptors, [`Error::Unsupported`] is
+    /// returned otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::Message;
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/", "Ping")?
+    ///     .append(&1u8)?
+    ///     .append(&"hello")?
+    ///     .append(&42u64)?
+    ///     .build()?;
+    /// let single = Message::method("/", "Ping")?.build(&(1u8, "hello", 42u64))?;
+    ///
+    /// assert_eq!(msg.body().signature().unwrap(), "yst");
+    /// assert_eq!(msg.body().data().bytes(), single.body().data().bytes());
+    /// assert_eq!(msg.body().deserialize::<(u8, String, u64)>()?, (1, "hello".into(), 42));
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn append<B>(self, value: &B) -> Result<AppendingBuilder<'a>>
+    where
+        B: serde::ser::Serialize + DynamicType,
+    {
+        AppendingBuilder {
+            builder: self,
+            body: AppendedBody::default(),
+        }
+        .append(value)
+    }
+
     /// Build the [`Message`] with the given body.
     ///
     /// You may pass `()` as the body if the message has no body.
     ///
     /// The caller is currently required to ensure that the resulting message contains the headers
     /// as compliant with the [specification]. Additional checks may be added to this builder over
-    /// time as needed.
+    /// time as needed. Currently, [`Error::InvalidField`] is returned for an error without an error
+    /// name or reply serial, a method return without a reply serial, and a signal with a reply
+    /// serial. The same checks apply to all the `build*` methods.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{message::{Builder, Type}, Error, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let call = Message::method("/", "Ping")?.build(&())?;
+    /// let reply = Message::method_reply(&call)?.build(&())?;
+    ///
+    /// // A signal built from the header of a reply would carry its reply serial.
+    /// let mut header = reply.header();
+    /// header.primary_mut().set_msg_type(Type::Signal);
+    /// assert_eq!(Builder::from(header).build(&()).unwrap_err(), Error::InvalidField);
+    /// # Ok(()) })().unwrap()
+    /// ```
     ///
     /// [specification]:
     /// https://dbus.freedesktop.org/doc/dbus-specification.html#message-protocol-header-fields
@@ -223,9 +515,100 @@ impl<'a> Builder<'a> {
         })
     }
 
+    /// Build the [`Message`] with `body` serialized for `signature`, attaching `fds`.
+    ///
+    /// Each `h` in `signature` is a placeholder for one of `fds`, serialized in `body` as the
+    /// `i32` index of the FD. The number of `h` in `signature` must match the number of `fds`, or
+    /// [`Error::FdCountMismatch`] is returned, so an array of FDs (`ah`) can only carry a single
+    /// FD. `body` must not contain any FD value itself, as these would be taken as indices.
+    ///
+    /// As with [`Builder::build_raw_body`], a `signature` consisting of a single STRUCT is taken
+    /// as the signature of the struct fields.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::{collections::HashMap, os::fd::OwnedFd};
+    /// # use slimbus::{zvariant::Value, Error, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let fd = OwnedFd::from(std::fs::File::open("/dev/null")?);
+    /// let options: HashMap<&str, Value> = HashMap::new();
+    /// let msg = Message::method("/org/freedesktop/portal/desktop", "OpenFile")?
+    ///     .build_with_fds(&("", 0i32, options), "sha{sv}", vec![fd.into()])?;
+    ///
+    /// assert_eq!(msg.body().signature().unwrap(), "sha{sv}");
+    /// assert_eq!(msg.header().unix_fds(), Some(1));
+    ///
+    /// let fd = OwnedFd::from(std::fs::File::open("/dev/null")?);
+    /// let msg = Message::method("/", "Close")?.build_with_fds(&(0i32,), "h", vec![fd.into()])?;
+    /// assert_eq!(msg.body().signature().unwrap(), "h");
+    ///
+    /// let res = Message::method("/", "Close")?.build_with_fds(&(0i32, 1i32), "hh", vec![]);
+    /// assert_eq!(res.unwrap_err(), Error::FdCountMismatch { expected: 2, actual: 0 });
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn build_with_fds<'b, B, S>(
+        self,
+        body: &B,
+        signature: S,
+        fds: Vec<OwnedFd>,
+    ) -> Result<Message>
+    where
+        B: serde::ser::Serialize + DynamicType,
+        S: TryInto<Signature<'b>>,
+        S::Error: Into<Error>,
+    {
+        let signature: Signature<'b> = signature.try_into().map_err(Into::into)?;
+        let placeholders = count_fds_in_signature(&signature);
+        if placeholders != fds.len() {
+            return Err(Error::FdCountMismatch {
+                expected: placeholders,
+                actual: fds.len(),
+            });
+        }
+
+        // The placeholders are serialized as plain integers, which have the same encoding, so the
+        // serializer doesn't take them for FDs. Tuples are serialized as a struct, which is
+        // encoded the same as a body of their fields.
+        let signature = body_signature(signature);
+        let placeholders_sig = signature.replace('h', "i");
+        let placeholders_sig = if body.dynamic_signature().starts_with(STRUCT_SIG_START_CHAR) {
+            format!("({placeholders_sig})")
+        } else {
+            placeholders_sig
+        };
+        let body = zvariant::to_bytes_for_signature(
+            dbus_context!(self, 0),
+            Signature::from_string_unchecked(placeholders_sig),
+            body,
+        )?;
+
+        // SAFETY: The body was serialized for `signature` with the FDs placeholders.
+        unsafe { self.build_raw_body(&body, signature, fds) }
+    }
+
     /// Create a new message from a raw slice of bytes to populate the body with, rather than by
     /// serializing a value. The message body will be the exact bytes.
     ///
+    /// As with [`Builder::build`], a `signature` consisting of a single STRUCT is taken as the
+    /// signature of the struct fields, the body not being wrapped in a struct.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::Message;
+    /// # (|| -> slimbus::Result<()> {
+    /// let built = Message::method("/", "Ping")?.build(&(42u32, "hello"))?;
+    /// let body = built.body();
+    /// let raw = unsafe {
+    ///     Message::method("/", "Ping")?.build_raw_body(body.data(), "(us)", vec![])?
+    /// };
+    ///
+    /// assert_eq!(raw.body().signature(), body.signature());
+    /// assert_eq!(raw.body().signature().unwrap(), "us");
+    /// # Ok(()) })().unwrap()
+    /// ```
+    ///
     /// # Safety
     ///
     /// This method is unsafe because it can be used to build an invalid message.
@@ -258,7 +641,7 @@ impl<'a> Builder<'a> {
 
     fn build_generic<WriteFunc>(
         self,
-        mut signature: Signature<'_>,
+        signature: Signature<'_>,
         body_size: serialized::Size,
         write_body: WriteFunc,
     ) -> Result<Message>
@@ -267,12 +650,10 @@ impl<'a> Builder<'a> {
     {
         let ctxt = dbus_context!(self, 0);
         let mut header = self.header;
+        check_fields(&header)?;
 
+        let signature = body_signature(signature);
         if !signature.is_empty() {
-            if signature.starts_with(zvariant::STRUCT_SIG_START_STR) {
-                // Remove leading and trailing STRUCT delimiters
-                signature = signature.slice(1..signature.len() - 1);
-            }
             header.fields_mut().add(Field::Signature(signature));
         }
 
@@ -280,15 +661,17 @@ impl<'a> Builder<'a> {
         header.primary_mut().set_body_len(body_len_u32);
 
         let fds_len = body_size.num_fds();
+        if fds_len as usize > self.max_fds {
+            return Err(Error::ExcessData);
+        }
         if fds_len != 0 {
             header.fields_mut().add(Field::UnixFDs(fds_len));
         }
 
         let hdr_len = *zvariant::serialized_size(ctxt, &header)?;
         // We need to align the body to 8-byte boundary.
-        let body_padding = padding_for_8_bytes(hdr_len);
-        let body_offset = hdr_len + body_padding;
-        let total_len = body_offset + body_size.size();
+        let (body_offset, total_len) = message_len(hdr_len, body_size.size())?;
+        let body_padding = body_offset - hdr_len;
         if total_len > MAX_MESSAGE_SIZE {
             return Err(Error::ExcessData);
         }
@@ -315,11 +698,77 @@ impl<'a> Builder<'a> {
                 bytes,
                 body_offset,
                 recv_seq: Sequence::default(),
+                recv_time: None,
+                header: OnceLock::new(),
             }),
         })
     }
 }
 
+// Check the fields that the type of the message requires or forbids.
+fn check_fields(header: &Header<'_>) -> Result<()> {
+    let valid = match header.message_type() {
+        Type::Error => header.error_name().is_some() && header.reply_serial().is_some(),
+        Type::MethodReturn => header.reply_serial().is_some(),
+        Type::Signal => header.reply_serial().is_none(),
+        Type::MethodCall => true,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidField)
+    }
+}
+
+impl<'a> AppendingBuilder<'a> {
+    /// Append `value` to the body of the message.
+    ///
+    /// See [`Builder::append`] for details.
+    pub fn append<B>(mut self, value: &B) -> Result<Self>
+    where
+        B: serde::ser::Serialize + DynamicType,
+    {
+        let builder = &self.builder;
+        let ctxt = dbus_context!(builder, self.body.bytes.len());
+        let mut bytes = Vec::new();
+        // SAFETY: The FDs are kept along the bytes, and put in the same Message when building.
+        let fds =
+            unsafe { zvariant::to_writer(&mut Cursor::new(&mut bytes), ctxt, value) }?.into_fds();
+        if !fds.is_empty() && !self.body.fds.is_empty() {
+            // The FD indices of `value` would have to be offset by the FDs already appended.
+            return Err(Error::Unsupported);
+        }
+
+        self.body.bytes.extend(bytes);
+        self.body
+            .signature
+            .push_str(value.dynamic_signature().as_str());
+        self.body.fds.extend(fds.into_iter().map(Arc::new));
+
+        Ok(self)
+    }
+
+    /// Build the [`Message`] with the appended values as its body.
+    pub fn build(self) -> Result<Message> {
+        let signature = Signature::try_from(self.body.signature)?;
+        let fds = self
+            .body
+            .fds
+            .into_iter()
+            .map(|fd| {
+                Arc::try_unwrap(fd).or_else(|fd| fd.as_fd().try_clone_to_owned().map(Into::into))
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        // SAFETY: The body was serialized for `signature` and `fds`.
+        unsafe {
+            self.builder
+                .build_raw_body(&self.body.bytes, signature, fds)
+        }
+    }
+}
+
 impl<'m> From<Header<'m>> for Builder<'m> {
     fn from(mut header: Header<'m>) -> Self {
         // Signature and Fds are added by body* methods.
@@ -327,6 +776,9 @@ impl<'m> From<Header<'m>> for Builder<'m> {
         fields.remove(FieldCode::Signature);
         fields.remove(FieldCode::UnixFDs);
 
-        Self { header }
+        Self {
+            header,
+            max_fds: DEFAULT_MAX_FDS,
+        }
     }
 }
@@ -7,53 +7,82 @@ use serde::{
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use zbus_names::{BusName, ErrorName, InterfaceName, MemberName, UniqueName};
-use zvariant::{ObjectPath, Signature, Type, Value};
+use zvariant::{serialized::Context, Endian, ObjectPath, Signature, Type, Value};
 
 /// The message field code.
 ///
-/// Every [`Field`] has an associated code. This is mostly an internal D-Bus protocol detail
+/// Every header field has an associated code. This is mostly an internal D-Bus protocol detail
 /// that you would not need to ever care about when using the high-level API. When using the
-/// low-level API, this is how you can [retrieve a specific field] from [`Fields`].
+/// low-level API, this is how you can tell which fields a message has, with
+/// [`Message::present_fields`].
 ///
-/// [`Field`]: enum.Field.html
-/// [retrieve a specific field]: struct.Fields.html#method.get_field
-/// [`Fields`]: struct.Fields.html
+/// [`Message::present_fields`]: super::Message::present_fields
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, Deserialize_repr, PartialEq, Eq, Serialize_repr, Type)]
-pub(super) enum FieldCode {
-    /// Code for [`Field::Path`](enum.Field.html#variant.Path)
+#[derive(Copy, Clone, Debug, Deserialize_repr, PartialEq, Eq, Hash, Serialize_repr, Type)]
+pub enum FieldCode {
+    /// The object to send a call to, or the object a signal is emitted from.
     Path = 1,
-    /// Code for [`Field::Interface`](enum.Field.html#variant.Interface)
+    /// The interface to invoke a method call on, or that a signal is emitted from.
     Interface = 2,
-    /// Code for [`Field::Member`](enum.Field.html#variant.Member)
+    /// The member, either the method name or signal name.
     Member = 3,
-    /// Code for [`Field::ErrorName`](enum.Field.html#variant.ErrorName)
+    /// The name of the error that occurred, for errors.
     ErrorName = 4,
-    /// Code for [`Field::ReplySerial`](enum.Field.html#variant.ReplySerial)
+    /// The serial number of the message this message is a reply to.
     ReplySerial = 5,
-    /// Code for [`Field::Destinatione`](enum.Field.html#variant.Destination)
+    /// The name of the connection this message is intended for.
     Destination = 6,
-    /// Code for [`Field::Sender`](enum.Field.html#variant.Sender)
+    /// Unique name of the sending connection.
     Sender = 7,
-    /// Code for [`Field::Signature`](enum.Field.html#variant.Signature)
+    /// The signature of the message body.
     Signature = 8,
-    /// Code for [`Field::UnixFDs`](enum.Field.html#variant.UnixFDs)
+    /// The number of Unix file descriptors that accompany the message.
     UnixFDs = 9,
 }
 
+impl TryFrom<u8> for FieldCode {
+    type Error = u8;
+
+    fn try_from(code: u8) -> Result<Self, u8> {
+        match code {
+            1 => Ok(FieldCode::Path),
+            2 => Ok(FieldCode::Interface),
+            3 => Ok(FieldCode::Member),
+            4 => Ok(FieldCode::ErrorName),
+            5 => Ok(FieldCode::ReplySerial),
+            6 => Ok(FieldCode::Destination),
+            7 => Ok(FieldCode::Sender),
+            8 => Ok(FieldCode::Signature),
+            9 => Ok(FieldCode::UnixFDs),
+            code => Err(code),
+        }
+    }
+}
+
 impl<'f> Field<'f> {
     /// Get the associated code for this field.
-    pub fn code(&self) -> FieldCode {
+    ///
+    /// Returns `None` for [`Field::Unknown`].
+    pub fn code(&self) -> Option<FieldCode> {
+        match self {
+            Field::Path(_) => Some(FieldCode::Path),
+            Field::Interface(_) => Some(FieldCode::Interface),
+            Field::Member(_) => Some(FieldCode::Member),
+            Field::ErrorName(_) => Some(FieldCode::ErrorName),
+            Field::ReplySerial(_) => Some(FieldCode::ReplySerial),
+            Field::Destination(_) => Some(FieldCode::Destination),
+            Field::Sender(_) => Some(FieldCode::Sender),
+            Field::Signature(_) => Some(FieldCode::Signature),
+            Field::UnixFDs(_) => Some(FieldCode::UnixFDs),
+            Field::Unknown(_, _) => None,
+        }
+    }
+
+    /// Get the code of this field as it appears on the wire.
+    pub fn raw_code(&self) -> u8 {
         match self {
-            Field::Path(_) => FieldCode::Path,
-            Field::Interface(_) => FieldCode::Interface,
-            Field::Member(_) => FieldCode::Member,
-            Field::ErrorName(_) => FieldCode::ErrorName,
-            Field::ReplySerial(_) => FieldCode::ReplySerial,
-            Field::Destination(_) => FieldCode::Destination,
-            Field::Sender(_) => FieldCode::Sender,
-            Field::Signature(_) => FieldCode::Signature,
-            Field::UnixFDs(_) => FieldCode::UnixFDs,
+            Field::Unknown(code, _) => *code,
+            field => field.code().map(|c| c as u8).unwrap_or_default(),
         }
     }
 }
@@ -69,7 +98,7 @@ impl<'f> Field<'f> {
 /// [headers]: struct.Header.html
 /// [are fixed]: struct.PrimaryHeader.html
 /// [Message Format]: https://dbus.freedesktop.org/doc/dbus-specification.html#message-protocol-messages
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub(super) enum Field<'f> {
     /// The object to send a call to, or the object a signal is emitted from.
     Path(ObjectPath<'f>),
@@ -89,6 +118,63 @@ pub(super) enum Field<'f> {
     Signature(Signature<'f>),
     /// The number of Unix file descriptors that accompany the message.
     UnixFDs(u32),
+    /// A field with a code not known to this crate.
+    ///
+    /// The value never contains file descriptors, which makes it infallibly clonable.
+    Unknown(u8, Value<'f>),
+}
+
+impl<'f> Clone for Field<'f> {
+    fn clone(&self) -> Self {
+        match self {
+            Field::Path(v) => Field::Path(v.clone()),
+            Field::Interface(v) => Field::Interface(v.clone()),
+            Field::Member(v) => Field::Member(v.clone()),
+            Field::ErrorName(v) => Field::ErrorName(v.clone()),
+            Field::ReplySerial(v) => Field::ReplySerial(*v),
+            Field::Destination(v) => Field::Destination(v.clone()),
+            Field::Sender(v) => Field::Sender(v.clone()),
+            Field::Signature(v) => Field::Signature(v.clone()),
+            Field::UnixFDs(v) => Field::UnixFDs(*v),
+            // Cloning a value only fails for FDs, which `Deserialize` and `Builder::raw_field`
+            // both reject for unknown fields.
+            Field::Unknown(code, v) => Field::Unknown(
+                *code,
+                v.try_clone().expect("unknown header field contains FDs"),
+            ),
+        }
+    }
+}
+
+impl Field<'_> {
+    // Convert to a field owning its value.
+    pub(super) fn into_owned(self) -> Field<'static> {
+        match self {
+            Field::Path(v) => Field::Path(v.into_owned()),
+            Field::Interface(v) => Field::Interface(v.into_owned()),
+            Field::Member(v) => Field::Member(v.into_owned()),
+            Field::ErrorName(v) => Field::ErrorName(v.into_owned()),
+            Field::ReplySerial(v) => Field::ReplySerial(v),
+            Field::Destination(v) => Field::Destination(v.into_owned()),
+            Field::Sender(v) => Field::Sender(v.into_owned()),
+            Field::Signature(v) => Field::Signature(v.into_owned()),
+            Field::UnixFDs(v) => Field::UnixFDs(v),
+            // Like cloning, converting a value only fails for FDs, see `Clone`.
+            Field::Unknown(code, v) => Field::Unknown(
+                code,
+                v.try_to_owned()
+                    .expect("unknown header field contains FDs")
+                    .into(),
+            ),
+        }
+    }
+}
+
+// Whether `value` holds file descriptors, possibly nested in containers or variants.
+pub(super) fn has_fds(value: &Value<'_>) -> bool {
+    let ctxt = Context::new_dbus(Endian::Little, 0);
+
+    zvariant::serialized_size(ctxt, value).map_or(true, |size| size.num_fds() > 0)
 }
 
 impl<'f> Type for Field<'f> {
@@ -102,19 +188,20 @@ impl<'f> Serialize for Field<'f> {
     where
         S: Serializer,
     {
-        let tuple: (FieldCode, Value<'_>) = match self {
-            Field::Path(value) => (FieldCode::Path, value.as_ref().into()),
-            Field::Interface(value) => (FieldCode::Interface, value.as_str().into()),
-            Field::Member(value) => (FieldCode::Member, value.as_str().into()),
-            Field::ErrorName(value) => (FieldCode::ErrorName, value.as_str().into()),
-            Field::ReplySerial(value) => (FieldCode::ReplySerial, value.get().into()),
-            Field::Destination(value) => (FieldCode::Destination, value.as_str().into()),
-            Field::Sender(value) => (FieldCode::Sender, value.as_str().into()),
-            Field::Signature(value) => (FieldCode::Signature, value.as_ref().into()),
-            Field::UnixFDs(value) => (FieldCode::UnixFDs, (*value).into()),
+        let value: Value<'_> = match self {
+            Field::Path(value) => value.as_ref().into(),
+            Field::Interface(value) => value.as_str().into(),
+            Field::Member(value) => value.as_str().into(),
+            Field::ErrorName(value) => value.as_str().into(),
+            Field::ReplySerial(value) => value.get().into(),
+            Field::Destination(value) => value.as_str().into(),
+            Field::Sender(value) => value.as_str().into(),
+            Field::Signature(value) => value.as_ref().into(),
+            Field::UnixFDs(value) => (*value).into(),
+            Field::Unknown(code, value) => return (code, value).serialize(serializer),
         };
 
-        tuple.serialize(serializer)
+        (self.raw_code(), value).serialize(serializer)
     }
 }
 
@@ -123,7 +210,12 @@ impl<'de: 'f, 'f> Deserialize<'de> for Field<'f> {
     where
         D: Deserializer<'de>,
     {
-        let (code, value) = <(FieldCode, Value<'_>)>::deserialize(deserializer)?;
+        let (code, value) = <(u8, Value<'_>)>::deserialize(deserializer)?;
+        let code = match FieldCode::try_from(code) {
+            Ok(code) => code,
+            Err(_) if has_fds(&value) => return Err(D::Error::custom(crate::Error::InvalidField)),
+            Err(code) => return Ok(Field::Unknown(code, value)),
+        };
         Ok(match code {
             FieldCode::Path => Field::Path(ObjectPath::try_from(value).map_err(D::Error::custom)?),
             FieldCode::Interface => {
@@ -132,27 +224,21 @@ impl<'de: 'f, 'f> Deserialize<'de> for Field<'f> {
             FieldCode::Member => {
                 Field::Member(MemberName::try_from(value).map_err(D::Error::custom)?)
             }
-            FieldCode::ErrorName => Field::ErrorName(
-                ErrorName::try_from(value)
-                    .map(Into::into)
-                    .map_err(D::Error::custom)?,
-            ),
+            FieldCode::ErrorName => {
+                Field::ErrorName(ErrorName::try_from(value).map_err(D::Error::custom)?)
+            }
             FieldCode::ReplySerial => {
                 let value = u32::try_from(value)
                     .map_err(D::Error::custom)
                     .and_then(|v| v.try_into().map_err(D::Error::custom))?;
                 Field::ReplySerial(value)
             }
-            FieldCode::Destination => Field::Destination(
-                BusName::try_from(value)
-                    .map(Into::into)
-                    .map_err(D::Error::custom)?,
-            ),
-            FieldCode::Sender => Field::Sender(
-                UniqueName::try_from(value)
-                    .map(Into::into)
-                    .map_err(D::Error::custom)?,
-            ),
+            FieldCode::Destination => {
+                Field::Destination(BusName::try_from(value).map_err(D::Error::custom)?)
+            }
+            FieldCode::Sender => {
+                Field::Sender(UniqueName::try_from(value).map_err(D::Error::custom)?)
+            }
             FieldCode::Signature => {
                 Field::Signature(Signature::try_from(value).map_err(D::Error::custom)?)
             }
@@ -1,10 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::num::NonZeroU32;
-use zbus_names::{BusName, ErrorName, InterfaceName, MemberName, UniqueName};
+use zbus_names::{InterfaceName, MemberName, UniqueName};
 use zvariant::{ObjectPath, Signature, Type};
 
 use crate::{
-    message::{Field, FieldCode, Header, Message},
+    message::{Field, FieldCode, Header, Message, PrimaryHeader},
     Result,
 };
 
@@ -35,8 +35,8 @@ impl<'m> Fields<'m> {
     ///
     /// [`Field`]: enum.Field.html
     pub fn replace<'f: 'm>(&mut self, field: Field<'f>) -> Option<Field<'m>> {
-        let code = field.code();
-        if let Some(found) = self.0.iter_mut().find(|f| f.code() == code) {
+        let code = field.raw_code();
+        if let Some(found) = self.0.iter_mut().find(|f| f.raw_code() == code) {
             return Some(std::mem::replace(found, field));
         }
         self.add(field);
@@ -56,14 +56,19 @@ impl<'m> Fields<'m> {
     ///
     /// [`Field`]: enum.Field.html
     pub fn get_field(&self, code: FieldCode) -> Option<&Field<'m>> {
-        self.0.iter().find(|f| f.code() == code)
+        self.0.iter().find(|f| f.code() == Some(code))
     }
 
     /// Remove the field matching the `code`.
     ///
     /// Returns `true` if a field was found and removed, `false` otherwise.
     pub(crate) fn remove(&mut self, code: FieldCode) -> bool {
-        match self.0.iter().enumerate().find(|(_, f)| f.code() == code) {
+        match self
+            .0
+            .iter()
+            .enumerate()
+            .find(|(_, f)| f.code() == Some(code))
+        {
             Some((i, _)) => {
                 self.0.remove(i);
 
@@ -102,6 +107,11 @@ impl FieldPos {
         }
     }
 
+    // Whether the field is present, an empty field being encoded as `start == end`.
+    pub fn is_present(&self) -> bool {
+        self.start <= self.end
+    }
+
     pub fn new<T>(msg_buf: &[u8], field: Option<&T>) -> Self
     where
         T: std::ops::Deref<Target = str>,
@@ -178,18 +188,10 @@ impl QuickFields {
         self.member.read(msg.data())
     }
 
-    pub fn error_name<'m>(&self, msg: &'m Message) -> Option<ErrorName<'m>> {
-        self.error_name.read(msg.data())
-    }
-
     pub fn reply_serial(&self) -> Option<NonZeroU32> {
         self.reply_serial
     }
 
-    pub fn destination<'m>(&self, msg: &'m Message) -> Option<BusName<'m>> {
-        self.destination.read(msg.data())
-    }
-
     pub fn sender<'m>(&self, msg: &'m Message) -> Option<UniqueName<'m>> {
         self.sender.read(msg.data())
     }
@@ -198,8 +200,55 @@ impl QuickFields {
         self.signature.read(msg.data())
     }
 
-    pub fn unix_fds(&self) -> Option<u32> {
-        self.unix_fds
+    // Reassemble the header of the message encoded in `buf`, from the cached fields.
+    pub fn header<'m>(&self, primary: &PrimaryHeader, buf: &'m [u8]) -> Header<'m> {
+        let mut fields = Fields::new();
+        if let Some(p) = self.path.read(buf) {
+            fields.add(Field::Path(p));
+        }
+        if let Some(i) = self.interface.read(buf) {
+            fields.add(Field::Interface(i));
+        }
+        if let Some(m) = self.member.read(buf) {
+            fields.add(Field::Member(m));
+        }
+        if let Some(e) = self.error_name.read(buf) {
+            fields.add(Field::ErrorName(e));
+        }
+        if let Some(r) = self.reply_serial {
+            fields.add(Field::ReplySerial(r));
+        }
+        if let Some(d) = self.destination.read(buf) {
+            fields.add(Field::Destination(d));
+        }
+        if let Some(s) = self.sender.read(buf) {
+            fields.add(Field::Sender(s));
+        }
+        if let Some(s) = self.signature.read(buf) {
+            fields.add(Field::Signature(s));
+        }
+        if let Some(u) = self.unix_fds {
+            fields.add(Field::UnixFDs(u));
+        }
+
+        Header::new(primary.clone(), fields)
+    }
+
+    // The codes of the fields present, in ascending order.
+    pub fn present(&self) -> impl Iterator<Item = FieldCode> {
+        [
+            (FieldCode::Path, self.path.is_present()),
+            (FieldCode::Interface, self.interface.is_present()),
+            (FieldCode::Member, self.member.is_present()),
+            (FieldCode::ErrorName, self.error_name.is_present()),
+            (FieldCode::ReplySerial, self.reply_serial.is_some()),
+            (FieldCode::Destination, self.destination.is_present()),
+            (FieldCode::Sender, self.sender.is_present()),
+            (FieldCode::Signature, self.signature.is_present()),
+            (FieldCode::UnixFDs, self.unix_fds.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(code, present)| present.then_some(code))
     }
 }
 
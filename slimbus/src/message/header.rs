@@ -15,12 +15,17 @@ use zvariant::{
 
 use crate::{
     message::{Field, FieldCode, Fields},
+    utils::padding_for_8_bytes,
     Error,
 };
 
-pub(crate) const PRIMARY_HEADER_SIZE: usize = 12;
+/// The size of the primary header, the fixed-size start of every message.
+///
+/// See [`Message::primary_header_bytes`](super::Message::primary_header_bytes) for its layout.
+pub const PRIMARY_HEADER_SIZE: usize = 12;
 pub(crate) const MIN_MESSAGE_SIZE: usize = PRIMARY_HEADER_SIZE + 4;
 pub(crate) const MAX_MESSAGE_SIZE: usize = 128 * 1024 * 1024; // 128 MiB
+pub(crate) const DEFAULT_MAX_FDS: usize = 253; // SCM_MAX_FD on Linux, as enforced by the daemon
 
 /// D-Bus code for endianness.
 #[repr(u8)]
@@ -131,7 +136,7 @@ impl PrimaryHeader {
             flags: BitFlags::empty(),
             protocol_version: 1,
             body_len,
-            serial_num: SERIAL_NUM.fetch_add(1, SeqCst).try_into().unwrap(),
+            serial_num: next_serial_num(),
         }
     }
 
@@ -146,6 +151,11 @@ impl PrimaryHeader {
     pub(crate) fn read_from_data(
         data: &serialized::Data<'_, '_>,
     ) -> Result<(PrimaryHeader, u32), Error> {
+        // A serial of 0 is zero in both endiannesses, so check it before it fails to deserialize
+        // into a `NonZeroU32` with a less helpful error.
+        if data.get(8..PRIMARY_HEADER_SIZE) == Some(&[0; 4]) {
+            return Err(Error::InvalidSerial);
+        }
         let (primary_header, size) = data.deserialize()?;
         assert_eq!(size, PRIMARY_HEADER_SIZE);
         let (fields_len, _) = data.slice(PRIMARY_HEADER_SIZE..).deserialize()?;
@@ -276,6 +286,16 @@ impl<'m> Header<'m> {
         self.primary
     }
 
+    // Convert to a header owning its fields.
+    pub(super) fn into_owned(self) -> Header<'static> {
+        let mut fields = Fields::new();
+        for field in self.fields.get() {
+            fields.add(field.clone().into_owned());
+        }
+
+        Header::new(self.primary, fields)
+    }
+
     /// Get a reference to the message fields.
     fn fields(&self) -> &Fields<'m> {
         &self.fields
@@ -343,3 +363,135 @@ impl<'m> Header<'m> {
 }
 
 static SERIAL_NUM: AtomicU32 = AtomicU32::new(1);
+
+// A process-wide unique serial number for a new message.
+pub(crate) fn next_serial_num() -> NonZeroU32 {
+    SERIAL_NUM.fetch_add(1, SeqCst).try_into().unwrap()
+}
+
+// The offset of the body and the total length of a message, with a header of `header_len` bytes
+// without the padding before the body, which always starts at an 8-byte boundary, and a body of
+// `body_len` bytes. Each step of the sum is checked, so lengths declared by a peer can not make it
+// wrap around, even on 32-bit targets.
+pub(crate) fn message_len(header_len: usize, body_len: usize) -> crate::Result<(usize, usize)> {
+    let body_offset = header_len
+        .checked_add(padding_for_8_bytes(header_len))
+        .ok_or(Error::ExcessData)?;
+    let total_len = body_offset.checked_add(body_len).ok_or(Error::ExcessData)?;
+
+    Ok((body_offset, total_len))
+}
+
+// The offset of the body and the total length of a message, as declared by a primary header
+// followed by `fields_len` bytes of header fields and announcing `body_len` bytes of body.
+pub(crate) fn declared_message_len(
+    fields_len: u32,
+    body_len: u32,
+) -> crate::Result<(usize, usize)> {
+    let header_len = MIN_MESSAGE_SIZE
+        .checked_add(fields_len as usize)
+        .ok_or(Error::ExcessData)?;
+
+    message_len(header_len, body_len as usize)
+}
+
+/// Read the primary header and the destination of an encoded message, without parsing the other
+/// header fields.
+///
+/// This is a fast path for routing: rather than deserializing all the header fields as
+/// [`crate::Message`] does, the fields array is scanned for the `DESTINATION` field, skipping the
+/// others. `bytes` only needs to hold the header, not the body. Header fields of unusual types,
+/// which only unknown fields can have, fall back to deserializing the whole header.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::message::{peek_destination, Type};
+/// # (|| -> slimbus::Result<()> {
+/// let msg = slimbus::Message::method("/org/example/Object", "Frobnicate")?
+///     .interface("org.example.Interface")?
+///     .destination("org.example.Service")?
+///     .build(&42u32)?;
+///
+/// let (primary, destination) = peek_destination(msg.data())?;
+/// assert_eq!(primary.msg_type(), Type::MethodCall);
+/// assert_eq!(destination.unwrap(), "org.example.Service");
+///
+/// let signal = slimbus::Message::signal("/", "org.example.Interface", "Changed")?.build(&())?;
+/// assert_eq!(peek_destination(signal.data())?.1, None);
+/// # Ok(()) })().unwrap()
+/// ```
+pub fn peek_destination(bytes: &[u8]) -> crate::Result<(PrimaryHeader, Option<BusName<'_>>)> {
+    let out_of_bounds = || Error::Variant(zvariant::Error::OutOfBounds);
+
+    let endian_sig = *bytes.first().ok_or_else(out_of_bounds)?;
+    let endian = Endian::from(EndianSig::try_from(endian_sig)?);
+    let header_bytes = bytes.get(..MIN_MESSAGE_SIZE).ok_or_else(out_of_bounds)?;
+    let (primary_header, fields_len) = PrimaryHeader::read(header_bytes)?;
+    let fields_end = MIN_MESSAGE_SIZE
+        .checked_add(fields_len as usize)
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(out_of_bounds)?;
+    let fields = &bytes[..fields_end];
+
+    let read_u32 = |pos: usize| -> crate::Result<u32> {
+        let bytes = fields.get(pos..pos + 4).ok_or_else(out_of_bounds)?;
+        let bytes = bytes.try_into().expect("slice of 4 bytes");
+        Ok(match endian {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        })
+    };
+    let align = |pos: usize, alignment: usize| pos.next_multiple_of(alignment);
+
+    let mut pos = MIN_MESSAGE_SIZE;
+    loop {
+        // Each field is a STRUCT of the code and a VARIANT, aligned to 8 bytes.
+        pos = align(pos, 8);
+        if pos >= fields_end {
+            return Ok((primary_header, None));
+        }
+        let code = fields[pos];
+        let sig_len = *fields.get(pos + 1).ok_or_else(out_of_bounds)? as usize;
+        let sig = fields
+            .get(pos + 2..pos + 2 + sig_len)
+            .ok_or_else(out_of_bounds)?;
+        // Skip the code, the signature length, the signature and its NUL terminator.
+        pos += 3 + sig_len;
+
+        pos = match sig {
+            b"s" | b"o" => {
+                pos = align(pos, 4);
+                let len = read_u32(pos)? as usize;
+                let start = pos + 4;
+                let end = start.checked_add(len).ok_or_else(out_of_bounds)?;
+                let value = fields.get(start..end).ok_or_else(out_of_bounds)?;
+                if code == FieldCode::Destination as u8 && sig == b"s" {
+                    let name = std::str::from_utf8(value)
+                        .map_err(|e| Error::Variant(zvariant::Error::Utf8(e)))?;
+                    return Ok((primary_header, Some(BusName::try_from(name)?)));
+                }
+
+                end + 1
+            }
+            b"g" => {
+                let len = *fields.get(pos).ok_or_else(out_of_bounds)? as usize;
+
+                pos + len + 2
+            }
+            b"y" => pos + 1,
+            b"n" | b"q" => align(pos, 2) + 2,
+            b"b" | b"i" | b"u" | b"h" => align(pos, 4) + 4,
+            b"x" | b"t" | b"d" => align(pos, 8) + 8,
+            _ => {
+                // Not worth skipping by hand, deserialize the whole header instead.
+                let ctxt = Context::new_dbus(endian, 0);
+                let data = serialized::Data::new(fields, ctxt);
+                let (header, _): (Header<'_>, _) = data.deserialize()?;
+                let destination = header.destination().map(|d| d.to_owned());
+
+                return Ok((primary_header, destination));
+            }
+        };
+    }
+}
@@ -1,16 +1,26 @@
 //! D-Bus Message.
-use std::{fmt, num::NonZeroU32, sync::Arc};
-
-use zbus_names::{ErrorName, InterfaceName, MemberName};
-use zvariant::{serialized, Endian};
-
-use crate::{utils::padding_for_8_bytes, zvariant::ObjectPath, Error, Result};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    num::NonZeroU32,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+use zbus_names::{ErrorName, InterfaceName, MemberName, OwnedUniqueName};
+use zvariant::{
+    serialized::{self, Context},
+    Endian,
+};
+
+use crate::{zvariant::ObjectPath, Error, Result};
 
 mod builder;
-pub use builder::Builder;
+pub use builder::{AppendingBuilder, Builder};
 
 mod field;
-use field::{Field, FieldCode};
+use field::Field;
+pub use field::FieldCode;
 
 mod fields;
 use fields::{Fields, QuickFields};
@@ -18,19 +28,93 @@ use fields::{Fields, QuickFields};
 mod body;
 pub use body::Body;
 
+mod borrowed;
+pub use borrowed::MessageRef;
+
+mod reply;
+pub use reply::ReplyContext;
+
 pub(crate) mod header;
-use header::MIN_MESSAGE_SIZE;
-pub use header::{EndianSig, Flags, Header, PrimaryHeader, Type, NATIVE_ENDIAN_SIG};
+pub use header::{
+    peek_destination, EndianSig, Flags, Header, PrimaryHeader, Type, NATIVE_ENDIAN_SIG,
+    PRIMARY_HEADER_SIZE,
+};
 
 /// A position in the stream of [`Message`] objects received by a single [`zbus::Connection`].
 ///
-/// Note: the relative ordering of values obtained from distinct [`zbus::Connection`] objects is
-/// not specified; only sequence numbers originating from the same connection should be compared.
-#[derive(Debug, Default, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+/// Each sequence is tagged with the connection it originates from, and only sequences from the
+/// same connection are ordered: comparing sequences from distinct connections with
+/// [`PartialOrd::partial_cmp`] returns `None` (and all of `<`, `<=`, `>` and `>=` are `false`).
+///
+/// Messages that were not received from a connection (e.g. built locally) all have the default
+/// sequence.
+///
+/// **Note**: `Sequence` doesn't implement [`Ord`], since sequences are only partially ordered. Code
+/// sorting messages by their sequence must compare them with [`PartialOrd`] and decide what to do
+/// with sequences from distinct connections.
+///
+/// # Example
+///
+/// ```
+/// use slimbus::{connection::socket::BoxedSplit, message::Sequence, Connection, Message};
+/// use slimbus::SocketReader;
+/// use std::{cmp::Ordering, os::{fd::AsRawFd, unix::net::UnixStream}};
+///
+/// // A connection sending to a reader over a socket pair.
+/// fn pair() -> (Connection, SocketReader) {
+///     let (a, b) = UnixStream::pair().unwrap();
+///     let (a_fd, b_fd) = (a.as_raw_fd(), b.as_raw_fd());
+///     let (read, write) = BoxedSplit::from(a).take();
+///     let (conn, _) = Connection::from_halves(write, read, vec![], false, a_fd);
+///     let (read, write) = BoxedSplit::from(b).take();
+///     let (_, reader) = Connection::from_halves(write, read, vec![], false, b_fd);
+///
+///     (conn, reader)
+/// }
+///
+/// let msg = Message::method("/", "Ping").unwrap().build(&()).unwrap();
+/// let (mut conn1, mut reader1) = pair();
+/// let (mut conn2, mut reader2) = pair();
+/// for _ in 0..2 {
+///     conn1.send(&msg).unwrap();
+/// }
+/// conn2.send(&msg).unwrap();
+///
+/// let first = reader1.read_socket().unwrap().recv_position();
+/// let second = reader1.read_socket().unwrap().recv_position();
+/// let other = reader2.read_socket().unwrap().recv_position();
+///
+/// assert!(first < second);
+/// assert_eq!(second.partial_cmp(&first), Some(Ordering::Greater));
+/// assert_eq!(first.partial_cmp(&other), None);
+/// assert!(!(first < other) && !(first > other) && first != other);
+/// assert_eq!(
+///     Sequence::default().partial_cmp(&Sequence::default()),
+///     Some(Ordering::Equal),
+/// );
+/// ```
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Sequence {
+    conn_id: u64,
     recv_seq: u64,
 }
 
+impl Sequence {
+    pub(crate) fn new(conn_id: u64, recv_seq: u64) -> Self {
+        Self { conn_id, recv_seq }
+    }
+}
+
+impl PartialOrd for Sequence {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        if self.conn_id != other.conn_id {
+            return None;
+        }
+
+        Some(self.recv_seq.cmp(&other.recv_seq))
+    }
+}
+
 /// A D-Bus Message.
 ///
 /// The content of the message are stored in serialized format. To get the body of the message, use
@@ -45,6 +129,29 @@ pub struct Sequence {
 /// deserialize to [`zvariant::OwnedFd`] the body (that you get using [`Message::body`]) if you want
 /// to keep the FDs around after the containing message is dropped.
 ///
+/// **Note**: Two messages compare equal (and hash the same) if their wire bytes are identical. The
+/// receive position and the FDs are not taken into account, since FDs can not be meaningfully
+/// compared.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::Message;
+/// # use std::collections::HashSet;
+/// # (|| -> slimbus::Result<()> {
+/// let ping = Message::method("/", "Ping")?.build(&())?;
+/// // The same bytes, parsed again, e.g. a duplicate delivery.
+/// let copy = Message::from_hex(&ping.to_hex())?;
+/// // The same call, but with another serial number.
+/// let other = Message::method("/", "Ping")?.build(&())?;
+///
+/// assert_eq!(ping, copy);
+/// assert_ne!(ping, other);
+/// let unique: HashSet<_> = [ping, copy, other].into_iter().collect();
+/// assert_eq!(unique.len(), 2);
+/// # Ok(()) })().unwrap()
+/// ```
+///
 /// [`Connection`]: struct.Connection#method.call_method
 #[derive(Clone)]
 pub struct Message {
@@ -57,6 +164,9 @@ pub(super) struct Inner {
     pub(crate) bytes: serialized::Data<'static, 'static>,
     pub(crate) body_offset: usize,
     pub(crate) recv_seq: Sequence,
+    pub(crate) recv_time: Option<Instant>,
+    // The header, if it was reassembled ahead of time by `Message::cache_header`.
+    pub(crate) header: OnceLock<Header<'static>>,
 }
 
 // TODO: Handle non-native byte order: https://github.com/dbus2/zbus/issues/19
@@ -92,50 +202,284 @@ impl Message {
     }
 
     /// Create a builder for message of type [`Type::MethodReturn`].
+    ///
+    /// The flags of `call` are not copied to the reply. To reply later without keeping `call`
+    /// around, see [`ReplyContext`].
     pub fn method_reply(call: &Self) -> Result<Builder<'_>> {
-        #[allow(deprecated)]
-        Builder::method_return(&call.header())
+        Builder::method_return_for(call)
+    }
+
+    /// Build the [`Type::MethodReturn`] reply to `call`, with `body`.
+    ///
+    /// This is a shorthand for [`Message::method_reply`] followed by [`Builder::build`]. The reply
+    /// serial, destination and endianness are taken from `call`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::Message;
+    /// # (|| -> slimbus::Result<()> {
+    /// let call = Message::method("/", "Add")?.sender(":1.42")?.build(&(1u32, 2u32))?;
+    /// let (a, b): (u32, u32) = call.body().deserialize()?;
+    /// let reply = Message::reply_with(&call, &(a + b))?;
+    ///
+    /// let header = reply.header();
+    /// assert_eq!(header.reply_serial(), Some(call.primary_header().serial_num()));
+    /// assert_eq!(header.destination().unwrap(), ":1.42");
+    /// assert_eq!(reply.body().deserialize::<u32>()?, 3);
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn reply_with<B>(call: &Self, body: &B) -> Result<Self>
+    where
+        B: serde::ser::Serialize + zvariant::DynamicType,
+    {
+        Builder::method_return_for(call)?.build(body)
     }
 
     /// Create a builder for message of type [`Type::Error`].
+    ///
+    /// Unlike [`Message::header`], this doesn't allocate to read the fields of `call`.
+    ///
+    /// Error names follow the syntax of interface names, and a malformed `name` is rejected with
+    /// [`Error::Names`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{Error, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let call = Message::method("/", "Frobnicate")?.sender(":1.42")?.build(&())?;
+    ///
+    /// assert!(Message::method_error(&call, "org.example.Error.Failed").is_ok());
+    /// assert!(matches!(
+    ///     Message::method_error(&call, "not a valid error name"),
+    ///     Err(Error::Names(_))
+    /// ));
+    /// assert!(Message::method_error(&call, "NoDots").is_err());
+    /// # Ok(()) })().unwrap()
+    /// ```
     pub fn method_error<'b, 'e: 'b, E>(call: &Self, name: E) -> Result<Builder<'b>>
     where
         E: TryInto<ErrorName<'e>>,
         E::Error: Into<Error>,
     {
-        #[allow(deprecated)]
-        Builder::error(&call.header(), name)
+        Builder::error_for(call, name)
     }
 
     /// Create a message from bytes.
     ///
     /// **Note:** Since the constructed message is not construct by zbus, the receive sequence,
     /// which can be acquired from [`Message::recv_position`], is not applicable and hence set
-    /// to `0`.
+    /// to `0`. Likewise, [`Message::recv_time`] returns `None`.
+    ///
+    /// The endianness of the context of `bytes` must match the one the message declares in its
+    /// first byte, or [`Error::IncorrectEndian`] is returned. Both endiannesses are supported. A
+    /// message with a serial number of 0, which is invalid, is rejected with
+    /// [`Error::InvalidSerial`], so any message can be replied to.
     ///
     /// # Safety
     ///
     /// This method is unsafe as bytes may have an invalid encoding.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{
+    /// #     zvariant::{serialized::{Context, Data}, Endian},
+    /// #     Error, Message,
+    /// # };
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/org/example/Object", "Frobnicate")?
+    ///     .endian(Endian::Big)
+    ///     .build(&("big", 42u32))?;
+    /// let bytes = msg.data().bytes().to_vec();
+    ///
+    /// let data = Data::new(bytes.clone(), Context::new_dbus(Endian::Big, 0));
+    /// let parsed = unsafe { Message::from_bytes(data) }?;
+    /// assert_eq!(parsed.body().deserialize::<(&str, u32)>()?, ("big", 42));
+    ///
+    /// let data = Data::new(bytes, Context::new_dbus(Endian::Little, 0));
+    /// assert!(matches!(
+    ///     unsafe { Message::from_bytes(data) },
+    ///     Err(Error::IncorrectEndian)
+    /// ));
+    ///
+    /// // The serial number is stored at offset 8.
+    /// let mut bytes = msg.data().bytes().to_vec();
+    /// bytes[8..12].fill(0);
+    /// let data = Data::new(bytes, Context::new_dbus(Endian::Big, 0));
+    /// assert!(matches!(
+    ///     unsafe { Message::from_bytes(data) },
+    ///     Err(Error::InvalidSerial)
+    /// ));
+    /// # Ok(()) })().unwrap()
+    /// ```
     pub unsafe fn from_bytes(bytes: serialized::Data<'static, 'static>) -> Result<Self> {
-        Self::from_raw_parts(bytes, 0)
+        Self::from_raw_parts(bytes, Sequence::default(), None)
+    }
+
+    /// Encode the wire bytes of the message as a lowercase hexadecimal string.
+    ///
+    /// This is a text form of the message for logs, bug reports and test fixtures, which
+    /// [`Message::from_hex`] parses back. The file descriptors the message carries can't be
+    /// encoded and are left out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{Error, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/org/example/Object", "Frobnicate")?.build(&("hex", 42u32))?;
+    /// let hex = msg.to_hex();
+    ///
+    /// let parsed = Message::from_hex(&hex)?;
+    /// assert_eq!(parsed, msg);
+    /// assert_eq!(parsed.body().deserialize::<(&str, u32)>()?, ("hex", 42));
+    /// assert!(Message::from_hex(&hex[..hex.len() - 2]).is_err());
+    /// assert!(matches!(Message::from_hex("not hex"), Err(Error::InvalidHex(_))));
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.inner.bytes.bytes())
+    }
+
+    /// Format the wire bytes of the message as an annotated hex dump, for debugging.
+    ///
+    /// Each line shows the offset and up to 16 bytes. The fields of the primary header are
+    /// labeled with their values, and the start and end of the header fields array, the padding
+    /// before the body and the body are marked. This shows exactly what is sent, e.g. when the
+    /// daemon rejects a message. The file descriptors the message carries are left out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::{zvariant::Endian, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/org/example/Object", "Ping")?
+    ///     .endian(Endian::Little)
+    ///     .build(&"pong")?;
+    /// let dump = msg.hexdump();
+    ///
+    /// assert!(dump.starts_with("00000000  6c 01 00 01"));
+    /// assert!(dump.contains("endianness 'l', type MethodCall, flags 0x00, version 1"));
+    /// assert!(dump.contains(&format!("serial {}", msg.primary_header().serial_num())));
+    /// assert!(dump.contains(&format!("body at offset {}", msg.body_offset())));
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn hexdump(&self) -> String {
+        let bytes = self.inner.bytes.bytes();
+        let primary = &self.inner.primary_header;
+        let body_offset = self.inner.body_offset;
+        let read_u32 = |pos: usize| {
+            let b = [bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]];
+            match primary.endian_sig() {
+                EndianSig::Little => u32::from_le_bytes(b),
+                EndianSig::Big => u32::from_be_bytes(b),
+            }
+        };
+        let fields_len = read_u32(PRIMARY_HEADER_SIZE) as usize;
+        let fields_end = PRIMARY_HEADER_SIZE + 4 + fields_len;
+
+        let sections = [
+            (
+                0,
+                4,
+                format!(
+                    "endianness '{}', type {:?}, flags {:#04x}, version {}",
+                    bytes[0] as char,
+                    primary.msg_type(),
+                    primary.flags().bits(),
+                    primary.protocol_version(),
+                ),
+            ),
+            (4, 8, format!("body length {}", primary.body_len())),
+            (
+                8,
+                PRIMARY_HEADER_SIZE,
+                format!("serial {}", primary.serial_num()),
+            ),
+            (
+                PRIMARY_HEADER_SIZE,
+                PRIMARY_HEADER_SIZE + 4,
+                format!("header fields array length {fields_len}"),
+            ),
+            (
+                PRIMARY_HEADER_SIZE + 4,
+                fields_end,
+                format!("header fields, ending at {fields_end:08x}"),
+            ),
+            (
+                fields_end,
+                body_offset,
+                "padding before the body".to_owned(),
+            ),
+            (
+                body_offset,
+                bytes.len(),
+                format!(
+                    "body at offset {body_offset}, signature {:?}",
+                    self.body()
+                        .signature()
+                        .map(|s| s.to_string())
+                        .unwrap_or_default(),
+                ),
+            ),
+        ];
+
+        let mut dump = String::new();
+        for (start, end, label) in sections {
+            if start == end {
+                continue;
+            }
+            for (i, line) in bytes[start..end].chunks(16).enumerate() {
+                let hex = line
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let label = if i == 0 { label.as_str() } else { "" };
+                dump.push_str(&format!("{:08x}  {hex:<47}  {label}", start + i * 16));
+                dump.truncate(dump.trim_end().len());
+                dump.push('\n');
+            }
+        }
+
+        dump
+    }
+
+    /// Parse a message from the hexadecimal encoding of its wire bytes, as produced by
+    /// [`Message::to_hex`].
+    ///
+    /// Unlike [`Message::from_bytes`], this is safe: the endianness is taken from the message
+    /// itself, and the string must hold exactly one message. The resulting message carries no
+    /// file descriptors, so deserializing any FD of its body fails.
+    ///
+    /// A string that isn't valid hexadecimal results in [`Error::InvalidHex`].
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = hex::decode(hex.trim()).map_err(Error::InvalidHex)?;
+        let endian_sig = bytes
+            .first()
+            .ok_or(Error::Variant(zvariant::Error::OutOfBounds))?;
+        let endian = Endian::from(EndianSig::try_from(*endian_sig)?);
+        let len = bytes.len();
+        let data = serialized::Data::new(bytes, Context::new_dbus(endian, 0));
+
+        let msg = Self::from_raw_parts(data, Sequence::default(), None)?;
+        let body_len = msg.primary_header().body_len() as usize;
+        if msg.body_offset() + body_len != len {
+            return Err(Error::ExcessData);
+        }
+
+        Ok(msg)
     }
 
     /// Create a message from its full contents
     pub(crate) fn from_raw_parts(
         bytes: serialized::Data<'static, 'static>,
-        recv_seq: u64,
+        recv_seq: Sequence,
+        recv_time: Option<Instant>,
     ) -> Result<Self> {
-        let endian = Endian::from(EndianSig::try_from(bytes[0])?);
-        if endian != bytes.context().endian() {
-            return Err(Error::IncorrectEndian);
-        }
-
-        let (primary_header, fields_len) = PrimaryHeader::read_from_data(&bytes)?;
-        let (header, _) = bytes.deserialize()?;
-
-        let header_len = MIN_MESSAGE_SIZE + fields_len as usize;
-        let body_offset = header_len + padding_for_8_bytes(header_len);
-        let quick_fields = QuickFields::new(&bytes, &header)?;
+        let (primary_header, quick_fields, body_offset) = parse_parts(&bytes)?;
 
         Ok(Self {
             inner: Arc::new(Inner {
@@ -143,7 +487,9 @@ impl Message {
                 quick_fields,
                 bytes,
                 body_offset,
-                recv_seq: Sequence { recv_seq },
+                recv_seq,
+                recv_time,
+                header: OnceLock::new(),
             }),
         })
     }
@@ -156,39 +502,22 @@ impl Message {
     ///
     /// Note: This method does not deserialize the header but it does currently allocate so its not
     /// zero-cost. While the allocation is small and will hopefully be removed in the future, it's
-    /// best to keep the header around if you need to access it a lot.
+    /// best to keep the header around if you need to access it a lot. For received messages, the
+    /// header can also be reassembled ahead of time, see
+    /// [`SocketReader::set_cache_headers`](crate::SocketReader::set_cache_headers).
     pub fn header(&self) -> Header<'_> {
-        let mut fields = Fields::new();
-        let quick_fields = &self.inner.quick_fields;
-        if let Some(p) = quick_fields.path(self) {
-            fields.add(Field::Path(p));
-        }
-        if let Some(i) = quick_fields.interface(self) {
-            fields.add(Field::Interface(i));
-        }
-        if let Some(m) = quick_fields.member(self) {
-            fields.add(Field::Member(m));
-        }
-        if let Some(e) = quick_fields.error_name(self) {
-            fields.add(Field::ErrorName(e));
-        }
-        if let Some(r) = quick_fields.reply_serial() {
-            fields.add(Field::ReplySerial(r));
-        }
-        if let Some(d) = quick_fields.destination(self) {
-            fields.add(Field::Destination(d));
-        }
-        if let Some(s) = quick_fields.sender(self) {
-            fields.add(Field::Sender(s));
-        }
-        if let Some(s) = quick_fields.signature(self) {
-            fields.add(Field::Signature(s));
-        }
-        if let Some(u) = quick_fields.unix_fds() {
-            fields.add(Field::UnixFDs(u));
+        if let Some(header) = self.inner.header.get() {
+            return header.clone();
         }
 
-        Header::new(self.inner.primary_header.clone(), fields)
+        self.inner
+            .quick_fields
+            .header(&self.inner.primary_header, self.data())
+    }
+
+    // Reassemble the header once, for `Message::header` to return a copy of it from then on.
+    pub(crate) fn cache_header(&self) {
+        self.inner.header.get_or_init(|| self.header().into_owned());
     }
 
     /// The message type.
@@ -232,24 +561,35 @@ impl Message {
         self.inner.quick_fields.reply_serial()
     }
 
+    /// The unique name of the sender of the message, as an owned name.
+    ///
+    /// Unlike [`Message::header`] with [`Header::sender`], this doesn't allocate the whole header
+    /// but only the name itself.
+    pub fn sender_owned(&self) -> Option<OwnedUniqueName> {
+        self.inner
+            .quick_fields
+            .sender(self)
+            .map(|s| s.to_owned().into())
+    }
+
     /// The body that you can deserialize using [`Body::deserialize`].
     ///
     /// # Example
     ///
     /// ```
-    /// # use zbus::message::Message;
-    /// # (|| -> zbus::Result<()> {
+    /// # use slimbus::message::Message;
+    /// # (|| -> slimbus::Result<()> {
     /// let send_body = (7i32, (2i32, "foo"), vec!["bar"]);
     /// let message = Message::method("/", "ping")?
     ///     .destination("zbus.test")?
     ///     .interface("zbus.test")?
     ///     .build(&send_body)?;
     /// let body = message.body();
-    /// let body: zbus::zvariant::Structure = body.deserialize()?;
+    /// let body: slimbus::zvariant::Structure = body.deserialize()?;
     /// let fields = body.fields();
-    /// assert!(matches!(fields[0], zvariant::Value::I32(7)));
-    /// assert!(matches!(fields[1], zvariant::Value::Structure(_)));
-    /// assert!(matches!(fields[2], zvariant::Value::Array(_)));
+    /// assert!(matches!(fields[0], slimbus::zvariant::Value::I32(7)));
+    /// assert!(matches!(fields[1], slimbus::zvariant::Value::Structure(_)));
+    /// assert!(matches!(fields[2], slimbus::zvariant::Value::Array(_)));
     ///
     /// let reply_body = Message::method_reply(&message)?.build(&body)?.body();
     /// let reply_value : (i32, (i32, &str), Vec<String>) = reply_body.deserialize()?;
@@ -270,16 +610,171 @@ impl Message {
         &self.inner.bytes
     }
 
+    /// The bytes of the primary header, the first [`PRIMARY_HEADER_SIZE`] bytes of the
+    /// [encoded message](Message::data).
+    ///
+    /// The layout is the same for both endiannesses, only the byte order of the integers differs:
+    ///
+    /// | Offset | Size | Field |
+    /// |--------|------|-------|
+    /// | 0 | 1 | Endianness signature, `b'l'` for little-endian or `b'B'` for big-endian |
+    /// | 1 | 1 | Message type |
+    /// | 2 | 1 | Flags |
+    /// | 3 | 1 | Major protocol version |
+    /// | 4 | 4 | Length of the body |
+    /// | 8 | 4 | Serial number |
+    ///
+    /// A forwarder can thus rewrite the serial of a message by patching bytes `8..12` of a copy of
+    /// the data, encoding the new serial in the byte order given by byte 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::message::{EndianSig, Message, PRIMARY_HEADER_SIZE};
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/org/example/Object", "Ping")?.build(&())?;
+    /// let primary = msg.primary_header_bytes();
+    /// assert_eq!(primary.len(), PRIMARY_HEADER_SIZE);
+    ///
+    /// let serial: [u8; 4] = primary[8..12].try_into().unwrap();
+    /// let serial = match msg.primary_header().endian_sig() {
+    ///     EndianSig::Little => u32::from_le_bytes(serial),
+    ///     EndianSig::Big => u32::from_be_bytes(serial),
+    /// };
+    /// assert_eq!(serial, msg.primary_header().serial_num().get());
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn primary_header_bytes(&self) -> &[u8] {
+        &self.inner.bytes[..PRIMARY_HEADER_SIZE]
+    }
+
+    /// The offset of the body in the [encoded message](Message::data).
+    ///
+    /// The body always starts at an 8-byte boundary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::message::Message;
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/org/example/Object", "Ping")?
+    ///     .interface("org.example.Peer")?
+    ///     .build(&("pong", 42u8))?;
+    ///
+    /// assert_eq!(msg.body_offset() % 8, 0);
+    /// assert_eq!(&msg.data()[msg.body_offset()..], &msg.body().data()[..]);
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn body_offset(&self) -> usize {
+        self.inner.body_offset
+    }
+
+    /// The length of the header in bytes, including the padding before the body.
+    ///
+    /// The header spans everything before the body, so this is the same value as
+    /// [`Message::body_offset`], seen as a length rather than a position: the header and the body
+    /// add up to the whole message.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::message::Message;
+    /// # (|| -> slimbus::Result<()> {
+    /// let msg = Message::method("/org/example/Object", "Ping")?.build(&("pong", 42u8))?;
+    ///
+    /// assert_eq!(msg.header_len() + msg.body().len(), msg.data().len());
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn header_len(&self) -> usize {
+        self.body_offset()
+    }
+
+    /// The codes of the header fields present in the message, in ascending order.
+    ///
+    /// Unknown fields, e.g. set with [`Builder::raw_field`], are not included.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use slimbus::message::{FieldCode, Message};
+    /// # (|| -> slimbus::Result<()> {
+    /// let signal = Message::signal("/org/example/Object", "org.example.Interface", "Changed")?
+    ///     .build(&42u32)?;
+    ///
+    /// assert!(signal.present_fields().eq([
+    ///     FieldCode::Path,
+    ///     FieldCode::Interface,
+    ///     FieldCode::Member,
+    ///     FieldCode::Signature,
+    /// ]));
+    /// assert!(!signal.present_fields().any(|code| code == FieldCode::ReplySerial));
+    /// # Ok(()) })().unwrap()
+    /// ```
+    pub fn present_fields(&self) -> impl Iterator<Item = FieldCode> {
+        self.inner.quick_fields.present()
+    }
+
     /// Get the receive ordering of a message.
     ///
-    /// This may be used to identify how two events were ordered on the bus.  It only produces a
-    /// useful ordering for messages that were produced by the same [`zbus::Connection`].
+    /// This may be used to identify how two events were ordered on the bus.  It only produces an
+    /// ordering for messages that were produced by the same [`zbus::Connection`]; see
+    /// [`Sequence`].
     ///
     /// This is completely unrelated to the serial number on the message, which is set by the peer
     /// and might not be ordered at all.
     pub fn recv_position(&self) -> Sequence {
         self.inner.recv_seq
     }
+
+    /// When the message was received.
+    ///
+    /// This is only recorded if enabled with [`SocketReader::set_record_recv_time`], and is `None`
+    /// otherwise, as well as for messages that were not received but built locally or created with
+    /// [`Message::from_bytes`].
+    ///
+    /// [`SocketReader::set_record_recv_time`]: crate::SocketReader::set_record_recv_time
+    pub fn recv_time(&self) -> Option<Instant> {
+        self.inner.recv_time
+    }
+}
+
+// Parse the headers of the message encoded in `bytes`, returning the primary header, the cached
+// fields and the offset of the body.
+fn parse_parts(bytes: &serialized::Data<'_, '_>) -> Result<(PrimaryHeader, QuickFields, usize)> {
+    let endian_sig = bytes
+        .first()
+        .ok_or(Error::Variant(zvariant::Error::OutOfBounds))?;
+    let endian = Endian::from(EndianSig::try_from(*endian_sig)?);
+    if endian != bytes.context().endian() {
+        return Err(Error::IncorrectEndian);
+    }
+
+    let (primary_header, fields_len) = PrimaryHeader::read_from_data(bytes)?;
+    // Don't trust the lengths in the primary header before checking them against the data.
+    let (body_offset, total_len) =
+        header::declared_message_len(fields_len, primary_header.body_len())?;
+    if total_len > bytes.len() {
+        return Err(Error::Variant(zvariant::Error::OutOfBounds));
+    }
+    let (header, _) = bytes.deserialize()?;
+
+    let quick_fields = QuickFields::new(bytes, &header)?;
+
+    Ok((primary_header, quick_fields, body_offset))
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.bytes.bytes() == other.inner.bytes.bytes()
+    }
+}
+
+impl Eq for Message {}
+
+impl Hash for Message {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.bytes.bytes().hash(state);
+    }
 }
 
 impl fmt::Debug for Message {
@@ -339,9 +834,10 @@ impl fmt::Display for Message {
                 }
 
                 let body = self.body();
-                let msg = body.deserialize_unchecked::<&str>();
-                if let Ok(msg) = msg {
-                    write!(f, ": {msg}")?;
+                if !body.is_empty() {
+                    if let Ok(msg) = body.deserialize_unchecked::<&str>() {
+                        write!(f, ": {msg}")?;
+                    }
                 }
             }
             Type::Signal => {
@@ -0,0 +1,108 @@
+use std::num::NonZeroU32;
+
+use enumflags2::BitFlags;
+use zbus_names::{ErrorName, OwnedUniqueName, UniqueName};
+
+use crate::{
+    message::{Builder, EndianSig, Flags, Message},
+    Error, Result,
+};
+
+/// What is needed to reply to a method call, without keeping the call around.
+///
+/// This is useful to reply later, after the call has been processed asynchronously. Besides the
+/// fields the reply is built from, the flags of the call are kept, so the service knows e.g.
+/// whether the caller allowed interactive authorization.
+///
+/// The flags of the call are never set on the reply. In particular, a reply never carries
+/// [`Flags::NoReplyExpected`].
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::message::{Flags, Message, ReplyContext};
+/// # (|| -> slimbus::Result<()> {
+/// let call = Message::method("/org/example/Object", "Delete")?
+///     .sender(":1.42")?
+///     .with_flags(Flags::AllowInteractiveAuth)?
+///     .build(&())?;
+/// let context = ReplyContext::new(&call);
+/// drop(call);
+///
+/// assert!(context.allow_interactive_auth());
+/// let reply = context.method_return()?.build(&())?;
+/// assert_eq!(reply.header().reply_serial(), Some(context.serial()));
+/// assert_eq!(reply.header().destination().unwrap(), ":1.42");
+/// assert!(reply.primary_header().flags().is_empty());
+/// # Ok(()) })().unwrap()
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplyContext {
+    serial: NonZeroU32,
+    endian: EndianSig,
+    sender: Option<OwnedUniqueName>,
+    flags: BitFlags<Flags>,
+}
+
+impl ReplyContext {
+    /// Capture the context of the method call `call`.
+    pub fn new(call: &Message) -> Self {
+        let primary = call.primary_header();
+
+        Self {
+            serial: primary.serial_num(),
+            endian: primary.endian_sig(),
+            sender: call.sender_owned(),
+            flags: primary.flags(),
+        }
+    }
+
+    /// The serial number of the call.
+    pub fn serial(&self) -> NonZeroU32 {
+        self.serial
+    }
+
+    /// The sender of the call, to which the reply is sent.
+    pub fn sender(&self) -> Option<&UniqueName<'static>> {
+        self.sender.as_deref()
+    }
+
+    /// The flags of the call.
+    pub fn flags(&self) -> BitFlags<Flags> {
+        self.flags
+    }
+
+    /// Whether the caller expects a reply.
+    pub fn expects_reply(&self) -> bool {
+        !self.flags.contains(Flags::NoReplyExpected)
+    }
+
+    /// Whether the caller allowed interactive authorization.
+    pub fn allow_interactive_auth(&self) -> bool {
+        self.flags.contains(Flags::AllowInteractiveAuth)
+    }
+
+    /// Create a builder for a reply of type [`Type::MethodReturn`](super::Type::MethodReturn).
+    pub fn method_return(&self) -> Result<Builder<'_>> {
+        Builder::method_return_for_context(self)
+    }
+
+    /// Create a builder for a reply of type [`Type::Error`](super::Type::Error).
+    pub fn error<'b, 'e: 'b, E>(&'b self, name: E) -> Result<Builder<'b>>
+    where
+        E: TryInto<ErrorName<'e>>,
+        E::Error: Into<Error>,
+    {
+        Builder::error_for_context(self, name)
+    }
+
+    pub(super) fn endian(&self) -> EndianSig {
+        self.endian
+    }
+}
+
+impl From<&Message> for ReplyContext {
+    fn from(call: &Message) -> Self {
+        Self::new(call)
+    }
+}
@@ -0,0 +1,21 @@
+//! D-Bus names and object paths.
+//!
+//! This re-exports the name types of [`zbus_names`], along with the object path types of
+//! [`zvariant`], so that all the D-Bus identifier types can be imported from one module.
+//!
+//! ```
+//! use slimbus::names::{BusName, InterfaceName, ObjectPath, OwnedObjectPath};
+//!
+//! let destination = BusName::try_from("org.freedesktop.DBus").unwrap();
+//! let interface = InterfaceName::try_from("org.freedesktop.DBus").unwrap();
+//! let path = ObjectPath::try_from("/org/freedesktop/DBus").unwrap();
+//! let owned = OwnedObjectPath::from(path.clone());
+//!
+//! assert_eq!(destination, "org.freedesktop.DBus");
+//! assert_eq!(interface, "org.freedesktop.DBus");
+//! assert_eq!(owned.as_str(), path.as_str());
+//! assert!(ObjectPath::try_from("not/a/path").is_err());
+//! ```
+
+pub use zbus_names::*;
+pub use zvariant::{ObjectPath, OwnedObjectPath};
@@ -0,0 +1,398 @@
+//! Serving method calls.
+//!
+//! The [`ObjectServer`] dispatches incoming method calls to the handlers registered for their
+//! object path, interface and member, and replies with the result.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use zbus_names::{InterfaceName, MemberName, OwnedInterfaceName, OwnedMemberName};
+use zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+
+use crate::{
+    fdo::{
+        self,
+        introspect::{Interface, Method, Node, Property, PropertyAccess},
+    },
+    message::{Flags, Type},
+    utils::machine_id,
+    Connection, DBusError, Error, Message, Result, SocketReader,
+};
+
+type Handler = Box<dyn Fn(&Message) -> fdo::Result<Message> + Send + Sync>;
+
+type Interfaces = HashMap<OwnedInterfaceName, HashMap<OwnedMemberName, Handler>>;
+
+type Properties = HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>;
+
+pub(crate) const PEER: &str = "org.freedesktop.DBus.Peer";
+const INTROSPECTABLE: &str = "org.freedesktop.DBus.Introspectable";
+const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+
+/// A dispatcher of method calls to handlers.
+///
+/// Handlers are registered for a method, identified by an object path, interface and member. They
+/// are given the method call and return either the reply, typically built with
+/// [`Message::method_reply`], or an [`fdo::Error`] that is turned into an error reply.
+///
+/// Calls to unknown objects, interfaces or methods get an error reply too.
+///
+/// The standard `org.freedesktop.DBus.Peer` and `org.freedesktop.DBus.Introspectable` interfaces
+/// are implemented for all objects, the introspection data listing the registered interfaces and
+/// methods. Objects for which properties were registered with
+/// [`ObjectServer::register_properties`] also implement `org.freedesktop.DBus.Properties`.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::{object_server::ObjectServer, Message};
+/// # (|| -> slimbus::Result<()> {
+/// let mut server = ObjectServer::new();
+/// server.register("/org/example/Echo", "org.example.Echo", "Echo", |call| {
+///     let text: String = call.body().deserialize()?;
+///     Ok(Message::method_reply(call)?.build(&text)?)
+/// })?;
+///
+/// let call = Message::method("/org/example/Echo", "Echo")?
+///     .interface("org.example.Echo")?
+///     .build(&"hello")?;
+/// let reply = server.dispatch(&call)?.unwrap();
+/// assert_eq!(reply.body().deserialize::<String>()?, "hello");
+///
+/// let call = Message::method("/org/example/Echo", "Shout")?
+///     .interface("org.example.Echo")?
+///     .build(&"hello")?;
+/// let reply = server.dispatch(&call)?.unwrap();
+/// assert_eq!(
+///     reply.header().error_name().unwrap(),
+///     "org.freedesktop.DBus.Error.UnknownMethod",
+/// );
+/// # Ok(()) })().unwrap()
+/// ```
+#[derive(Default)]
+pub struct ObjectServer {
+    objects: HashMap<OwnedObjectPath, Interfaces>,
+    properties: HashMap<OwnedObjectPath, Properties>,
+}
+
+impl ObjectServer {
+    /// Create an `ObjectServer` without any handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `handler` for calls to `member` of `interface` on the object at `path`.
+    ///
+    /// A handler previously registered for the same method is replaced.
+    pub fn register<'p, 'i, 'm, P, I, M, F>(
+        &mut self,
+        path: P,
+        interface: I,
+        member: M,
+        handler: F,
+    ) -> Result<()>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        M: TryInto<MemberName<'m>>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+        M::Error: Into<Error>,
+        F: Fn(&Message) -> fdo::Result<Message> + Send + Sync + 'static,
+    {
+        let path = path.try_into().map_err(Into::into)?.into();
+        let interface = interface.try_into().map_err(Into::into)?.into();
+        let member = member.try_into().map_err(Into::into)?.into();
+
+        self.objects
+            .entry(path)
+            .or_default()
+            .entry(interface)
+            .or_default()
+            .insert(member, Box::new(handler));
+
+        Ok(())
+    }
+
+    /// Register the `properties` of `interface` on the object at `path`.
+    ///
+    /// The properties are read-only, served through the `org.freedesktop.DBus.Properties`
+    /// interface. Properties previously registered for the same interface are replaced.
+    pub fn register_properties<'p, 'i, P, I>(
+        &mut self,
+        path: P,
+        interface: I,
+        properties: HashMap<String, OwnedValue>,
+    ) -> Result<()>
+    where
+        P: TryInto<ObjectPath<'p>>,
+        I: TryInto<InterfaceName<'i>>,
+        P::Error: Into<Error>,
+        I::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?.into();
+        let interface = interface.try_into().map_err(Into::into)?.into();
+
+        self.properties
+            .entry(path)
+            .or_default()
+            .insert(interface, properties);
+
+        Ok(())
+    }
+
+    /// Dispatch the method call `call` to its handler, returning the reply to send.
+    ///
+    /// Returns `None` if `msg` is not a method call, or if the caller expects no reply. In the
+    /// latter case, the handler is still run.
+    pub fn dispatch(&self, call: &Message) -> Result<Option<Message>> {
+        if call.message_type() != Type::MethodCall {
+            return Ok(None);
+        }
+
+        reply_to(call, self.handle(call))
+    }
+
+    /// Read a message from `reader` and, if it is a method call, dispatch it and send the reply
+    /// over `conn`.
+    ///
+    /// Messages other than method calls are returned to the caller.
+    pub fn serve(
+        &self,
+        conn: &mut Connection,
+        reader: &mut SocketReader,
+    ) -> Result<Option<Message>> {
+        let msg = reader.read_socket()?;
+        if msg.message_type() != Type::MethodCall {
+            return Ok(Some(msg));
+        }
+
+        if let Some(reply) = self.dispatch(&msg)? {
+            conn.send(&reply)?;
+        }
+
+        Ok(None)
+    }
+
+    fn handle(&self, call: &Message) -> fdo::Result<Message> {
+        let header = call.header();
+        let path = header.path().ok_or(fdo::Error::ZBus(Error::MissingField))?;
+        let member = header
+            .member()
+            .ok_or(fdo::Error::ZBus(Error::MissingField))?;
+        let path = OwnedObjectPath::from(path.to_owned());
+
+        match header.interface().map(|i| i.as_str()) {
+            Some(PEER) => return handle_peer(call, member),
+            Some(INTROSPECTABLE) => return self.handle_introspectable(call, &path, member),
+            Some(PROPERTIES) if self.properties.contains_key(&path) => {
+                return self.handle_properties(call, &path, member)
+            }
+            _ => (),
+        }
+
+        let interfaces = self
+            .objects
+            .get(&path)
+            .ok_or_else(|| fdo::Error::UnknownObject(format!("Unknown object `{path}`")))?;
+        let handler = match header.interface() {
+            Some(interface) => interfaces
+                .get(interface.as_str())
+                .ok_or_else(|| {
+                    fdo::Error::UnknownInterface(format!("Unknown interface `{interface}`"))
+                })?
+                .get(member.as_str()),
+            // Without an interface, the method is looked up in all interfaces of the object.
+            None => interfaces
+                .values()
+                .find_map(|members| members.get(member.as_str())),
+        }
+        .ok_or_else(|| fdo::Error::UnknownMethod(format!("Unknown method `{member}`")))?;
+
+        handler(call)
+    }
+
+    fn handle_introspectable(
+        &self,
+        call: &Message,
+        path: &OwnedObjectPath,
+        member: &MemberName<'_>,
+    ) -> fdo::Result<Message> {
+        if member.as_str() != "Introspect" {
+            return Err(fdo::Error::UnknownMethod(format!(
+                "Unknown method `{member}`"
+            )));
+        }
+
+        let xml = self.introspect(path)?.to_xml();
+
+        Ok(Message::reply_with(call, &xml)?)
+    }
+
+    fn handle_properties(
+        &self,
+        call: &Message,
+        path: &OwnedObjectPath,
+        member: &MemberName<'_>,
+    ) -> fdo::Result<Message> {
+        let properties = &self.properties[path];
+        let interface_properties = |interface: &str| {
+            properties.get(interface).ok_or_else(|| {
+                fdo::Error::UnknownInterface(format!("Unknown interface `{interface}`"))
+            })
+        };
+        let reply = Message::method_reply(call)?;
+        let body = call.body();
+
+        match member.as_str() {
+            "Get" => {
+                let (interface, name): (&str, &str) = body.deserialize()?;
+                let value = interface_properties(interface)?.get(name).ok_or_else(|| {
+                    fdo::Error::UnknownProperty(format!("Unknown property `{name}`"))
+                })?;
+
+                Ok(reply.build(value)?)
+            }
+            "GetAll" => {
+                let interface: &str = body.deserialize()?;
+
+                Ok(reply.build(interface_properties(interface)?)?)
+            }
+            "Set" => {
+                let (interface, name, _): (&str, &str, OwnedValue) = body.deserialize()?;
+                if !interface_properties(interface)?.contains_key(name) {
+                    return Err(fdo::Error::UnknownProperty(format!(
+                        "Unknown property `{name}`"
+                    )));
+                }
+
+                Err(fdo::Error::PropertyReadOnly(format!(
+                    "Property `{name}` is read-only"
+                )))
+            }
+            _ => Err(fdo::Error::UnknownMethod(format!(
+                "Unknown method `{member}`"
+            ))),
+        }
+    }
+
+    // The introspection data of the object at `path`.
+    fn introspect(&self, path: &OwnedObjectPath) -> fdo::Result<Node> {
+        let prefix = match path.as_str() {
+            "/" => "/".to_string(),
+            path => format!("{path}/"),
+        };
+        let paths = self.objects.keys().chain(self.properties.keys());
+        let children: BTreeSet<_> = paths
+            .filter_map(|p| p.as_str().strip_prefix(&prefix))
+            .filter_map(|rest| rest.split('/').next())
+            .filter(|child| !child.is_empty())
+            .collect();
+        let is_object = self.objects.contains_key(path) || self.properties.contains_key(path);
+        if !is_object && children.is_empty() {
+            return Err(fdo::Error::UnknownObject(format!(
+                "Unknown object `{path}`"
+            )));
+        }
+
+        let properties = self.properties.get(path);
+        let mut node = Node::new();
+        if is_object {
+            node = node.add_standard_interfaces(properties.is_some());
+        }
+        let handled = self.objects.get(path).into_iter().flat_map(|i| i.keys());
+        let with_properties = properties.into_iter().flat_map(|p| p.keys());
+        let names: BTreeSet<_> = handled.chain(with_properties).collect();
+        for name in names {
+            let mut interface = Interface::new(name.as_str())?;
+            if let Some(members) = self.objects.get(path).and_then(|i| i.get(name)) {
+                let mut members: Vec<_> = members.keys().collect();
+                members.sort();
+                for member in members {
+                    interface = interface.add_method(Method::new(member.as_str())?);
+                }
+            }
+            if let Some(values) = properties.and_then(|p| p.get(name)) {
+                let mut values: Vec<_> = values.iter().collect();
+                values.sort_by(|a, b| a.0.cmp(b.0));
+                for (property, value) in values {
+                    let ty = value.value_signature();
+                    interface =
+                        interface.add_property(Property::new(property, ty, PropertyAccess::Read)?);
+                }
+            }
+            node = node.add_interface(interface);
+        }
+        for child in children {
+            node = node.add_node(Node::new().set_name(child));
+        }
+
+        Ok(node)
+    }
+}
+
+// The reply to send for the call `call` to the `Peer` interface, if the caller expects one.
+pub(crate) fn reply_to_peer(call: &Message) -> Result<Option<Message>> {
+    let reply = match call.header().member() {
+        Some(member) => handle_peer(call, member),
+        None => Err(fdo::Error::ZBus(Error::MissingField)),
+    };
+
+    reply_to(call, reply)
+}
+
+// The reply to send for `call` given the result of its handler, if the caller expects one.
+fn reply_to(call: &Message, reply: fdo::Result<Message>) -> Result<Option<Message>> {
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err(e) => e.create_reply(&call.header())?,
+    };
+    if call
+        .primary_header()
+        .flags()
+        .contains(Flags::NoReplyExpected)
+    {
+        return Ok(None);
+    }
+
+    Ok(Some(reply))
+}
+
+fn handle_peer(call: &Message, member: &MemberName<'_>) -> fdo::Result<Message> {
+    let reply = Message::method_reply(call)?;
+
+    match member.as_str() {
+        "Ping" => Ok(reply.build(&())?),
+        "GetMachineId" => {
+            let id = machine_id()
+                .map_err(|e| fdo::Error::IOError(format!("Failed to read machine ID: {e}")))?;
+
+            Ok(reply.build(&id)?)
+        }
+        _ => Err(fdo::Error::UnknownMethod(format!(
+            "Unknown method `{member}`"
+        ))),
+    }
+}
+
+impl fmt::Debug for ObjectServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let methods: Vec<_> = self
+            .objects
+            .iter()
+            .flat_map(|(path, interfaces)| {
+                interfaces.iter().flat_map(move |(interface, members)| {
+                    members
+                        .keys()
+                        .map(move |member| format!("{path}:{interface}.{member}"))
+                })
+            })
+            .collect();
+
+        f.debug_struct("ObjectServer")
+            .field("methods", &methods)
+            .finish()
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    io,
+    os::fd::RawFd,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use nix::libc;
+use zvariant::{Signature, STRUCT_SIG_START_CHAR};
+
+pub mod signature;
+
+pub(crate) const FDS_MAX: usize = 1024; // this is hardcoded in sdbus - nothing in the spec
+
+// The effective user ID of the process.
+//
+// This is the only place the user ID is queried, for the default session bus address, the
+// `EXTERNAL` authentication ID and the home directory of the cookie keyring.
+pub(crate) fn euid() -> libc::uid_t {
+    // SAFETY: `geteuid` has no preconditions and always succeeds.
+    unsafe { libc::geteuid() }
+}
+
+// The home directory of the user, from `$HOME` or the user database.
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    match std::env::var("HOME") {
+        Ok(home) => Some(home.into()),
+        Err(_) => unix::home_dir(),
+    }
+}
+
+mod unix {
+    use std::ffi::{CStr, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+
+    pub(super) fn home_dir() -> Option<PathBuf> {
+        let uid = super::euid();
+        let passwd = unsafe { nix::libc::getpwuid(uid) };
+
+        // getpwnam(3):
+        // The getpwnam() and getpwuid() functions return a pointer to a passwd structure, or NULL
+        // if the matching entry is not found or an error occurs. If an error occurs, errno is set
+        // to indicate the error. If one wants to check errno after the call, it should be set to
+        // zero before the call. The return value may point to a static area, and may be overwritten
+        // by subsequent calls to getpwent(3), getpwnam(), or getpwuid().
+        if passwd.is_null() {
+            return None;
+        }
+
+        // SAFETY: `getpwuid()` returns either NULL or a valid pointer to a `passwd` structure.
+        let passwd = unsafe { &*passwd };
+        if passwd.pw_dir.is_null() {
+            return None;
+        }
+
+        // SAFETY: `getpwuid()->pw_dir` is a valid pointer to a c-string.
+        let home_dir = unsafe { CStr::from_ptr(passwd.pw_dir) };
+
+        Some(PathBuf::from(OsStr::from_bytes(home_dir.to_bytes())))
+    }
+}
+
+// The ID of the machine, as used by D-Bus.
+pub(crate) fn machine_id() -> io::Result<String> {
+    let id = std::fs::read_to_string("/etc/machine-id")
+        .or_else(|_| std::fs::read_to_string("/var/lib/dbus/machine-id"))?;
+
+    Ok(id.trim().to_owned())
+}
+
+pub(crate) fn padding_for_8_bytes(value: usize) -> usize {
+    padding_for_n_bytes(value, 8)
+}
+
+pub(crate) fn padding_for_n_bytes(value: usize, align: usize) -> usize {
+    let len_rounded_up = value.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1);
+
+    len_rounded_up.wrapping_sub(value)
+}
+
+/// The signature of a message body with the given type signature.
+///
+/// A message body is a sequence of values rather than a single struct, so if `signature` is a
+/// single STRUCT, its leading and trailing delimiters are removed. Other signatures, including ones
+/// with several STRUCTs such as `(i)(s)`, are returned as is.
+pub(crate) fn body_signature(signature: Signature<'_>) -> Signature<'_> {
+    let mut types = signature::complete_types(&signature);
+    match (types.next(), types.next()) {
+        (Some(ty), None) if ty.starts_with(STRUCT_SIG_START_CHAR) => {
+            signature.slice(1..signature.len() - 1)
+        }
+        _ => signature,
+    }
+}
+
+/// Wait up to `timeout` for `fd` to become readable.
+///
+/// Returns `false` if the timeout expired first.
+pub(crate) fn wait_readable(fd: RawFd, timeout: Duration) -> io::Result<bool> {
+    wait_any_readable(&[fd], timeout).map(|readable| readable.is_some())
+}
+
+/// Wait up to `timeout` for any of `fds` to become readable.
+///
+/// Returns the index of the first readable file descriptor, or `None` if the timeout expired first.
+/// A timeout too large to represent, such as `Duration::MAX`, waits indefinitely. Interrupted
+/// waits are retried for the rest of the timeout.
+pub(crate) fn wait_any_readable(fds: &[RawFd], timeout: Duration) -> io::Result<Option<usize>> {
+    let mut fds: Vec<_> = fds
+        .iter()
+        .map(|&fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    let deadline = Instant::now().checked_add(timeout);
+
+    loop {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                // Round up, so the deadline is not missed by waiting less than a millisecond.
+                let millis = remaining.as_micros().div_ceil(1000);
+                millis.try_into().unwrap_or(i32::MAX)
+            }
+            None => -1,
+        };
+        let res = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout) };
+        if res < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        return Ok(fds.iter().position(|fd| fd.revents != 0));
+    }
+}
+
+/// Convert a file descriptor received in a message body into a specific type, e.g. a
+/// [`std::fs::File`] or a [`std::os::unix::net::UnixStream`].
+///
+/// Ownership of the file descriptor is transferred to the returned value, which closes it when
+/// dropped. Since deserializing a [`zvariant::OwnedFd`] from a [`crate::message::Body`] duplicates
+/// the descriptor, the result stays valid independently of the [`crate::Message`], which keeps (and
+/// eventually closes) its own copy.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn f(msg: slimbus::Message) -> slimbus::Result<()> {
+/// let fd: slimbus::zvariant::OwnedFd = msg.body().deserialize()?;
+/// let file: std::fs::File = slimbus::owned_fd_into(fd);
+/// # Ok(()) }
+/// ```
+pub fn owned_fd_into<T>(fd: zvariant::OwnedFd) -> T
+where
+    T: From<std::os::fd::OwnedFd>,
+{
+    T::from(fd.into())
+}
+
+/// Send a status notification to the service manager, following the systemd [`sd_notify`]
+/// protocol.
+///
+/// A service started by systemd with `Type=notify` signals it's ready by sending `READY=1`, which a
+/// D-Bus service typically does once it has acquired its well-known name. `state` is written as is
+/// to the `AF_UNIX` datagram socket given by the `NOTIFY_SOCKET` environment variable, either a
+/// path or, if it starts with `@`, an abstract socket name.
+///
+/// This is a no-op if `NOTIFY_SOCKET` is not set, i.e. if the service was not started by a service
+/// manager expecting notifications. Other kinds of sockets, such as `vsock:` ones, result in
+/// [`crate::Error::Unsupported`].
+///
+/// [`sd_notify`]: https://www.freedesktop.org/software/systemd/man/latest/sd_notify.html
+///
+/// # Example
+///
+/// ```
+/// # (|| -> slimbus::Result<()> {
+/// let path = std::env::temp_dir().join(format!("slimbus-notify-{}", std::process::id()));
+/// let manager = std::os::unix::net::UnixDatagram::bind(&path)?;
+///
+/// std::env::set_var("NOTIFY_SOCKET", &path);
+/// slimbus::sd_notify("READY=1")?;
+/// let mut buf = [0; 16];
+/// let len = manager.recv(&mut buf)?;
+/// assert_eq!(&buf[..len], b"READY=1");
+///
+/// std::env::remove_var("NOTIFY_SOCKET");
+/// slimbus::sd_notify("READY=1")?;
+/// # std::fs::remove_file(path)?;
+/// # Ok(()) })().unwrap()
+/// ```
+pub fn sd_notify(state: &str) -> crate::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(address) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+    let socket = UnixDatagram::unbound()?;
+    match address.as_encoded_bytes() {
+        [b'/', ..] => {
+            socket.send_to(state.as_bytes(), address)?;
+        }
+        #[cfg(target_os = "linux")]
+        [b'@', name @ ..] => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+            let address = SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?;
+        }
+        _ => return Err(crate::Error::Unsupported),
+    }
+
+    Ok(())
+}
+
+/// Helper trait for macro-generated code.
+///
+/// This trait allows macros to refer to the `Ok` and `Err` types of a [Result] that is behind a
+/// type alias.  This is currently required because the macros for properties expect a Result
+/// return value, but the macro-generated `receive_` functions need to refer to the actual
+/// type without the associated error.
+#[doc(hidden)]
+pub trait ResultAdapter {
+    type Ok;
+    type Err;
+}
+
+impl<T, E> ResultAdapter for Result<T, E> {
+    type Ok = T;
+    type Err = E;
+}
@@ -0,0 +1,91 @@
+//! Splitting signatures into complete types and inspecting them.
+
+use zvariant::{
+    Signature, ARRAY_SIGNATURE_CHAR, DICT_ENTRY_SIG_END_CHAR, DICT_ENTRY_SIG_START_CHAR,
+    STRUCT_SIG_END_CHAR, STRUCT_SIG_START_CHAR,
+};
+
+/// Iterate over the top-level complete types of `signature`.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::{signature::complete_types, zvariant::Signature};
+/// let signature = Signature::try_from("ii(ss)a{sv}aai").unwrap();
+/// let types: Vec<_> = complete_types(&signature).collect();
+///
+/// assert_eq!(types, ["i", "i", "(ss)", "a{sv}", "aai"]);
+/// ```
+pub fn complete_types<'s>(signature: &Signature<'s>) -> CompleteTypes<'s> {
+    CompleteTypes {
+        signature: signature.clone(),
+        pos: 0,
+    }
+}
+
+/// Count the file descriptors (`h`) in `signature`.
+///
+/// FDs are counted at any depth in structs, dict entries and arrays, but an array only counts
+/// for the FDs of a single element, since the number of elements is only known from the data.
+/// Likewise, FDs inside variants (`v`) can not be counted since their signature is part of the
+/// data, so this is only the exact count of FDs of a value without arrays and variants.
+///
+/// # Example
+///
+/// ```
+/// # use slimbus::{signature::count_fds_in_signature, zvariant::Signature};
+/// let count = |s| count_fds_in_signature(&Signature::try_from(s).unwrap());
+///
+/// assert_eq!(count("sha{sv}"), 1);
+/// assert_eq!(count("(hh)a{sh}"), 3);
+/// assert_eq!(count("v"), 0);
+/// ```
+pub fn count_fds_in_signature(signature: &Signature<'_>) -> usize {
+    signature.as_bytes().iter().filter(|c| **c == b'h').count()
+}
+
+/// An iterator over the top-level complete types of a signature.
+///
+/// This is created by [`complete_types`].
+#[derive(Debug, Clone)]
+pub struct CompleteTypes<'s> {
+    signature: Signature<'s>,
+    pos: usize,
+}
+
+impl<'s> Iterator for CompleteTypes<'s> {
+    type Item = Signature<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.signature.len() {
+            return None;
+        }
+
+        let start = self.pos;
+        self.pos = complete_type_end(self.signature.as_bytes(), start);
+
+        Some(self.signature.slice(start..self.pos))
+    }
+}
+
+// The end of the complete type starting at `pos`. `Signature` is always valid, so the end of the
+// signature is only ever reached after a complete type.
+fn complete_type_end(bytes: &[u8], pos: usize) -> usize {
+    match bytes[pos] as char {
+        ARRAY_SIGNATURE_CHAR => complete_type_end(bytes, pos + 1),
+        STRUCT_SIG_START_CHAR | DICT_ENTRY_SIG_START_CHAR => {
+            let mut pos = pos + 1;
+            while pos < bytes.len()
+                && !matches!(
+                    bytes[pos] as char,
+                    STRUCT_SIG_END_CHAR | DICT_ENTRY_SIG_END_CHAR
+                )
+            {
+                pos = complete_type_end(bytes, pos);
+            }
+
+            pos + 1
+        }
+        _ => pos + 1,
+    }
+}